@@ -0,0 +1,177 @@
+//#[setting(...)] 字段属性
+
+pub struct SettingField<'a> {
+    pub ident: &'a syn::Ident,
+    pub ty: &'a syn::Type,
+    //json 中的键, 同时也是前端使用的名字
+    pub key: String,
+    pub default: Option<syn::Expr>,
+    //不保存也不暴露给前端
+    pub skip: bool,
+    //保存但不在设置列表中显示
+    pub hidden: bool,
+    //前端不能修改
+    pub readonly: bool,
+    //字段本身也是一个设置结构体, 通过 `group.item` 访问
+    pub group: bool,
+    //前端翻译用的键, 默认为 `setting.<name>.label` 和 `setting.<name>.description`
+    pub label: Option<String>,
+    pub description: Option<String>,
+}
+
+pub fn get_fields(st: &syn::DeriveInput) -> syn::Result<Vec<SettingField<'_>>> {
+    let named = if let syn::Data::Struct(syn::DataStruct {
+        fields: syn::Fields::Named(syn::FieldsNamed { ref named, .. }),
+        ..
+    }) = st.data
+    {
+        named
+    } else {
+        return syn::Result::Err(syn::Error::new_spanned(
+            st,
+            "Setting derive macro can only be used on structs with named fields",
+        ));
+    };
+
+    let mut fields: Vec<SettingField> = Vec::new();
+    for field in named {
+        let field = parse_field(field)?;
+        if !field.skip && fields.iter().any(|f| !f.skip && f.key == field.key) {
+            return syn::Result::Err(syn::Error::new_spanned(
+                field.ident,
+                format!("duplicate setting name `{}`", field.key),
+            ));
+        }
+        fields.push(field);
+    }
+    syn::Result::Ok(fields)
+}
+
+fn parse_field(field: &syn::Field) -> syn::Result<SettingField<'_>> {
+    let ident = field.ident.as_ref().unwrap();
+    let mut rename: Option<syn::LitStr> = None;
+    let mut setting = SettingField {
+        ident,
+        ty: &field.ty,
+        key: ident.to_string(),
+        default: None,
+        skip: false,
+        hidden: false,
+        readonly: false,
+        group: false,
+        label: None,
+        description: None,
+    };
+
+    for attr in &field.attrs {
+        if !attr.path().is_ident("setting") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let name: syn::LitStr = meta.value()?.parse()?;
+                if name.value().is_empty() || name.value().contains('.') {
+                    return Err(syn::Error::new_spanned(
+                        &name,
+                        "setting name can not be empty or contain `.`",
+                    ));
+                }
+                rename = Some(name);
+            } else if meta.path.is_ident("default") {
+                setting.default = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("skip") {
+                setting.skip = true;
+            } else if meta.path.is_ident("hidden") {
+                setting.hidden = true;
+            } else if meta.path.is_ident("readonly") {
+                setting.readonly = true;
+            } else if meta.path.is_ident("group") {
+                setting.group = true;
+            } else if meta.path.is_ident("label") {
+                setting.label = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            } else if meta.path.is_ident("description") {
+                setting.description = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            } else {
+                return Err(meta.error(
+                    "expected one of `rename`, `default`, `skip`, `hidden`, `readonly`, `group`, `label`, `description`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+
+    if let Some(name) = &rename {
+        setting.key = name.value();
+    }
+    if setting.skip {
+        let conflict = if rename.is_some() {
+            Some("rename")
+        } else if setting.hidden {
+            Some("hidden")
+        } else if setting.readonly {
+            Some("readonly")
+        } else if setting.group {
+            Some("group")
+        } else if setting.label.is_some() {
+            Some("label")
+        } else if setting.description.is_some() {
+            Some("description")
+        } else {
+            None
+        };
+        if let Some(conflict) = conflict {
+            return syn::Result::Err(syn::Error::new_spanned(
+                ident,
+                format!("`skip` can not be used together with `{}`", conflict),
+            ));
+        }
+    }
+    if setting.group
+        && let Some(default) = &setting.default
+    {
+        return syn::Result::Err(syn::Error::new_spanned(
+            default,
+            "`default` can not be used on a setting group",
+        ));
+    }
+    syn::Result::Ok(setting)
+}
+
+//生成 items() 中的一项
+pub fn items_field(field: &SettingField) -> proc_macro2::TokenStream {
+    let ty = field.ty;
+    let key = &field.key;
+    if field.skip || field.hidden {
+        quote::quote!()
+    } else if field.group {
+        quote::quote! {
+            items.extend(<#ty>::items().into_iter().map(|item| format!("{}.{}", #key, item)));
+        }
+    } else {
+        quote::quote!(items.push(#key.to_string());)
+    }
+}
+
+//生成 item_readonly(), 设置项不存在时返回 None, 隐藏的设置项也可以修改
+pub fn item_readonly_fn(fields: &[SettingField]) -> proc_macro2::TokenStream {
+    let leaf: Vec<_> = fields.iter().filter(|f| !f.skip && !f.group).collect();
+    let leaf_key_vec: Vec<_> = leaf.iter().map(|f| &f.key).collect();
+    let leaf_readonly_vec: Vec<_> = leaf.iter().map(|f| f.readonly).collect();
+    let group: Vec<_> = fields.iter().filter(|f| f.group).collect();
+    let group_key_vec: Vec<_> = group.iter().map(|f| &f.key).collect();
+    let group_type_vec: Vec<_> = group.iter().map(|f| f.ty).collect();
+    quote::quote! {
+        pub fn item_readonly(item_name: &str) -> Option<bool> {
+            if let Some((group, rest)) = item_name.split_once('.') {
+                return match group {
+                    #(#group_key_vec => <#group_type_vec>::item_readonly(rest),)*
+                    _ => None,
+                };
+            }
+            match item_name {
+                #(#leaf_key_vec => Some(#leaf_readonly_vec),)*
+                _ => None,
+            }
+        }
+    }
+}
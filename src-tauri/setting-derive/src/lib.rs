@@ -1,14 +1,16 @@
 use proc_macro::TokenStream;
 
+mod attrs;
 mod modpack_setting_derive;
+mod schema;
 mod setting_derive;
 
-#[proc_macro_derive(Setting)]
+#[proc_macro_derive(Setting, attributes(setting))]
 pub fn setting(input: TokenStream) -> TokenStream {
     setting_derive::setting(input)
 }
 
-#[proc_macro_derive(ModpackSetting)]
+#[proc_macro_derive(ModpackSetting, attributes(setting))]
 pub fn modpack_setting(input: TokenStream) -> TokenStream {
     modpack_setting_derive::modpack_setting(input)
 }
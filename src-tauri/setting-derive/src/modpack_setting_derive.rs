@@ -1,5 +1,7 @@
 use proc_macro::TokenStream;
 
+use crate::attrs::{SettingField, get_fields};
+
 pub fn modpack_setting(input: TokenStream) -> TokenStream {
     let st = syn::parse_macro_input!(input as syn::DeriveInput);
     match expand(&st) {
@@ -10,12 +12,59 @@ pub fn modpack_setting(input: TokenStream) -> TokenStream {
 fn expand(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let struct_name = &st.ident;
     let struct_fields = get_fields(st)?;
-    let fields_name_vec: Vec<_> = struct_fields.iter().map(|f| &f.ident).collect();
-    let fields_type_vec: Vec<_> = struct_fields.iter().map(|f| get_option_fields(&f.ty)).collect();
+    let fields_name_vec: Vec<_> = struct_fields.iter().map(|f| f.ident).collect();
+    let keys: Vec<_> = struct_fields
+        .iter()
+        .filter(|f| !f.skip)
+        .map(|f| f.key.clone())
+        .collect();
+    let schema = crate::schema::expand(st, &keys)?;
+
+    let read_vec = struct_fields
+        .iter()
+        .map(read_field)
+        .collect::<syn::Result<Vec<_>>>()?;
+    let create_vec: Vec<_> = struct_fields.iter().map(create_field).collect();
+    let save_vec: Vec<_> = struct_fields.iter().map(save_field).collect();
+    let items_vec: Vec<_> = struct_fields
+        .iter()
+        .map(crate::attrs::items_field)
+        .collect();
+
+    let leaf: Vec<_> = struct_fields
+        .iter()
+        .filter(|f| !f.skip && !f.group)
+        .collect();
+    let leaf_name_vec: Vec<_> = leaf.iter().map(|f| f.ident).collect();
+    let leaf_key_vec: Vec<_> = leaf.iter().map(|f| &f.key).collect();
+    let leaf_change_vec = leaf
+        .iter()
+        .map(|f| change_field(f))
+        .collect::<syn::Result<Vec<_>>>()?;
+    let group: Vec<_> = struct_fields.iter().filter(|f| f.group).collect();
+    let group_name_vec: Vec<_> = group.iter().map(|f| f.ident).collect();
+    let group_key_vec: Vec<_> = group.iter().map(|f| &f.key).collect();
+    let resettable_name_vec: Vec<_> = leaf
+        .iter()
+        .filter(|f| !f.readonly)
+        .map(|f| f.ident)
+        .collect();
+    let leaf_reset_vec: Vec<_> = leaf.iter().map(|f| reset_field(f)).collect();
+
+    let item_readonly = crate::attrs::item_readonly_fn(&struct_fields);
+    let export_vec: Vec<_> = struct_fields.iter().map(export_field).collect();
+    let import_vec = struct_fields
+        .iter()
+        .map(import_field)
+        .collect::<syn::Result<Vec<_>>>()?;
+
     let rec = quote::quote! {
         impl #struct_name{
+            #schema
+            #item_readonly
+
             pub fn read(json: serde_json::Value) -> anyhow::Result<Self>{
-                #(let #fields_name_vec = #fields_type_vec::read_modpack(json.get(stringify!(#fields_name_vec)).cloned())?;)*
+                #(let #fields_name_vec = #read_vec;)*
 
                 Ok(Self {
                     #(#fields_name_vec,)*
@@ -25,40 +74,111 @@ fn expand(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
             }
             pub fn create() -> anyhow::Result<Self> {
                 Ok(Self {
-                    #(#fields_name_vec: None,)*
+                    #(#fields_name_vec: #create_vec,)*
                 })
             }
             pub fn save(&self) -> anyhow::Result<serde_json::Value> {
                 let mut json_data: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
-                #(
-                    if let Some(ref item) = self.#fields_name_vec {
-                        json_data.insert(stringify!(#fields_name_vec).to_string(), item.write()?);
-                    }
-                )*
+                json_data.insert("schema_version".to_string(), serde_json::json!(Self::SCHEMA_VERSION));
+                #(#save_vec)*
                 let result: serde_json::Value = serde_json::Value::Object(json_data);
                 Ok(result)
             }
+            //导出覆盖了全局设置的设置项, 只读和跳过的设置项不导出
+            pub fn export(&self) -> anyhow::Result<serde_json::Value> {
+                let mut json_data: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
+                #(#export_vec)*
+                Ok(serde_json::Value::Object(json_data))
+            }
+            //从配置文件导入, replace 为 true 时配置文件中没有的设置项重新使用全局设置
+            //出错时可能已经导入了一部分, 调用者应当在副本上导入
+            pub fn import(&mut self, profile: &serde_json::Value, replace: bool, globle: &Settings) -> anyhow::Result<()> {
+                self.import_item(profile, replace, "", globle)
+            }
+            #[doc(hidden)]
+            pub fn import_item(&mut self, profile: &serde_json::Value, replace: bool, prefix: &str, globle: &Settings) -> anyhow::Result<()> {
+                #(#import_vec)*
+                Ok(())
+            }
+            //前端可以看到的设置项, 分组中的设置项为 `group.item`
+            pub fn items() -> Vec<String> {
+                let mut items = Vec::new();
+                #(#items_vec)*
+                items
+            }
             pub fn get(&self, item_name: String, globle: &Settings) -> anyhow::Result<serde_json::Value> {
-                match item_name.as_str() {
-                    #(stringify!(#fields_name_vec) => match &self.#fields_name_vec{
+                self.get_item(&item_name, &item_name, globle)
+            }
+            pub fn change(&mut self, item_name: String, value: serde_json::Value, globle: &Settings) -> anyhow::Result<()> {
+                self.change_item(&item_name, &item_name, value, globle)
+            }
+            //设置项是否覆盖了全局设置, 设置项不存在时返回 None
+            pub fn is_overridden(&self, item_name: &str) -> Option<bool> {
+                if let Some((group, rest)) = item_name.split_once('.') {
+                    return match group {
+                        #(#group_key_vec => self.#group_name_vec.is_overridden(rest),)*
+                        _ => None,
+                    };
+                }
+                match item_name {
+                    #(#leaf_key_vec => Some(self.#leaf_name_vec.is_some()),)*
+                    _ => None,
+                }
+            }
+            //取消覆盖, 重新使用全局设置, 分组名会重置整个分组
+            //只读的设置项和 change 一样返回错误
+            pub fn reset(&mut self, item_name: &str) -> anyhow::Result<()> {
+                self.reset_item(item_name, item_name)
+            }
+            #[doc(hidden)]
+            pub fn reset_item(&mut self, path: &str, full_name: &str) -> anyhow::Result<()> {
+                if let Some((group, rest)) = path.split_once('.') {
+                    return match group {
+                        #(#group_key_vec => self.#group_name_vec.reset_item(rest, full_name),)*
+                        _ => Err(anyhow::anyhow!("Item not found")),
+                    };
+                }
+                match path {
+                    #(#leaf_key_vec => #leaf_reset_vec,)*
+                    #(#group_key_vec => {
+                        self.#group_name_vec.reset_all();
+                        Ok(())
+                    })*
+                    _ => Err(anyhow::anyhow!("Item not found")),
+                }
+            }
+            //取消所有覆盖, 只读的设置项保持不变
+            pub fn reset_all(&mut self) {
+                #(self.#resettable_name_vec = None;)*
+                #(self.#group_name_vec.reset_all();)*
+            }
+            //path 是在当前分组中的名字, full_name 用来从全局设置中读取
+            #[doc(hidden)]
+            pub fn get_item(&self, path: &str, full_name: &str, globle: &Settings) -> anyhow::Result<serde_json::Value> {
+                if let Some((group, rest)) = path.split_once('.') {
+                    return match group {
+                        #(#group_key_vec => self.#group_name_vec.get_item(rest, full_name, globle),)*
+                        _ => Err(anyhow::anyhow!("Item not found")),
+                    };
+                }
+                match path {
+                    #(#leaf_key_vec => match &self.#leaf_name_vec{
                         Some(v) => v.send(),
-                        None => globle.get(item_name),
+                        None => globle.get(full_name.to_string()),
                     },)*
                     _ => Err(anyhow::anyhow!("Item not found")),
                 }
             }
-            pub fn change(&mut self, item_name: String, value: Vec<String>, globle: &Settings) -> anyhow::Result<()> {
-                match item_name.as_str() {
-                    #(stringify!(#fields_name_vec) => match &mut self.#fields_name_vec{
-                        Some(v) => v.receive(value),
-                        None => {
-                            let mut item_value = globle.get(item_name)?;
-                            let mut item = #fields_type_vec::read(Some(item_value))?;
-                            item.receive(value)?;
-                            self.#fields_name_vec = Some(item);
-                            Ok(())
-                        },
-                    },)*
+            #[doc(hidden)]
+            pub fn change_item(&mut self, path: &str, full_name: &str, value: serde_json::Value, globle: &Settings) -> anyhow::Result<()> {
+                if let Some((group, rest)) = path.split_once('.') {
+                    return match group {
+                        #(#group_key_vec => self.#group_name_vec.change_item(rest, full_name, value, globle),)*
+                        _ => Err(anyhow::anyhow!("Item not found")),
+                    };
+                }
+                match path {
+                    #(#leaf_key_vec => #leaf_change_vec,)*
                     _ => Err(anyhow::anyhow!("Item not found")),
                 }
             }
@@ -67,34 +187,175 @@ fn expand(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     syn::Result::Ok(rec)
 }
 
-fn get_fields(
-    st: &syn::DeriveInput,
-) -> syn::Result<&syn::punctuated::Punctuated<syn::Field, syn::Token![,]>> {
-    if let syn::Data::Struct(syn::DataStruct {
-        fields: syn::Fields::Named(syn::FieldsNamed { ref named, .. }),
+fn read_field(field: &SettingField) -> syn::Result<proc_macro2::TokenStream> {
+    let ty = field.ty;
+    let key = &field.key;
+    if field.skip {
+        return syn::Result::Ok(create_field(field));
+    }
+    if field.group {
+        return syn::Result::Ok(quote::quote! {
+            <#ty>::read(json.get(#key).cloned().unwrap_or_else(|| serde_json::json!({})))?
+        });
+    }
+    let inner = get_option_fields(ty)?;
+    syn::Result::Ok(match &field.default {
+        Some(default) => quote::quote! {
+            match json.get(#key) {
+                Some(value) => <#inner>::read_modpack(Some(value.clone()))?,
+                None => #default,
+            }
+        },
+        None => quote::quote!(<#inner>::read_modpack(json.get(#key).cloned())?),
+    })
+}
+
+fn create_field(field: &SettingField) -> proc_macro2::TokenStream {
+    let ty = field.ty;
+    match &field.default {
+        Some(default) => quote::quote!(#default),
+        None if field.skip => quote::quote!(::core::default::Default::default()),
+        None if field.group => quote::quote!(<#ty>::create()?),
+        None => quote::quote!(None),
+    }
+}
+
+fn save_field(field: &SettingField) -> proc_macro2::TokenStream {
+    let ident = field.ident;
+    let key = &field.key;
+    if field.skip {
+        quote::quote!()
+    } else if field.group {
+        //分组中没有覆盖任何设置时不写入
+        quote::quote! {
+            let mut group_value = self.#ident.save()?;
+            if let Some(group) = group_value.as_object_mut() {
+                group.remove("schema_version");
+                if !group.is_empty() {
+                    json_data.insert(#key.to_string(), group_value);
+                }
+            }
+        }
+    } else {
+        quote::quote! {
+            if let Some(ref item) = self.#ident {
+                json_data.insert(#key.to_string(), item.write()?);
+            }
+        }
+    }
+}
+
+//只读的设置项不能取消覆盖, 错误和 change 相同
+fn reset_field(field: &SettingField) -> proc_macro2::TokenStream {
+    let ident = field.ident;
+    if field.readonly {
+        quote::quote!(Err(anyhow::anyhow!("Item is readonly: {}", full_name)))
+    } else {
+        quote::quote! {{
+            self.#ident = None;
+            Ok(())
+        }}
+    }
+}
+
+//没有覆盖时先复制全局设置再修改
+fn change_field(field: &SettingField) -> syn::Result<proc_macro2::TokenStream> {
+    if field.readonly {
+        return syn::Result::Ok(quote::quote!(Err(anyhow::anyhow!(
+            "Item is readonly: {}",
+            full_name
+        ))));
+    }
+    let ident = field.ident;
+    let inner = get_option_fields(field.ty)?;
+    syn::Result::Ok(quote::quote! {
+        match &mut self.#ident{
+            Some(v) => v.receive(value),
+            None => {
+                let item_value = globle.get(full_name.to_string())?;
+                let mut item = <#inner>::read(Some(item_value))?;
+                item.receive(value)?;
+                self.#ident = Some(item);
+                Ok(())
+            },
+        }
+    })
+}
+
+fn get_option_fields(ty: &syn::Type) -> syn::Result<&syn::Type> {
+    if let syn::Type::Path(syn::TypePath {
+        path: syn::Path { segments, .. },
         ..
-    }) = st.data
+    }) = ty
+        && let Some(seg) = segments.last()
+        && seg.ident == "Option"
+        && let syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
+            args, ..
+        }) = &seg.arguments
+        && let Some(syn::GenericArgument::Type(inner_type)) = args.first()
     {
-        syn::Result::Ok(named)
+        return syn::Result::Ok(inner_type);
+    }
+    syn::Result::Err(syn::Error::new_spanned(
+        ty,
+        "ModpackSetting fields must be `Option<T>`, use #[setting(group)] or #[setting(skip)] for other fields",
+    ))
+}
+
+fn export_field(field: &SettingField) -> proc_macro2::TokenStream {
+    let ident = field.ident;
+    let key = &field.key;
+    if field.skip || field.readonly {
+        quote::quote!()
+    } else if field.group {
+        quote::quote! {
+            let group_value = self.#ident.export()?;
+            if group_value.as_object().is_some_and(|group| !group.is_empty()) {
+                json_data.insert(#key.to_string(), group_value);
+            }
+        }
     } else {
-        syn::Result::Err(syn::Error::new_spanned(
-            st,
-            "Setting derive macro can only be used on structs with named fields",
-        ))
+        quote::quote! {
+            if let Some(ref item) = self.#ident {
+                json_data.insert(#key.to_string(), item.export()?);
+            }
+        }
     }
 }
 
-fn get_option_fields(ty: &syn::Type ) -> &syn::Type{
-    if let syn::Type::Path(syn::TypePath { path: syn::Path{segments, ..}, .. }) = ty {
-        if let Some(seg) = segments.last() {
-            if seg.ident == "Option" {
-                if let syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments{args,..}) = &seg.arguments {
-                    if let Some(syn::GenericArgument::Type(inner_type)) = args.first() {
-                        return inner_type;
+//没有覆盖时以全局设置为基础导入
+fn import_field(field: &SettingField) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = field.ident;
+    let key = &field.key;
+    if field.skip || field.readonly {
+        return syn::Result::Ok(quote::quote!());
+    }
+    if field.group {
+        return syn::Result::Ok(quote::quote! {
+            self.#ident.import_item(
+                profile.get(#key).unwrap_or(&serde_json::Value::Null),
+                replace,
+                &format!("{}{}.", prefix, #key),
+                globle,
+            )?;
+        });
+    }
+    let inner = get_option_fields(field.ty)?;
+    let create = create_field(field);
+    syn::Result::Ok(quote::quote! {
+        match profile.get(#key) {
+            Some(value) => {
+                let item = match &self.#ident {
+                    Some(current) => <#inner>::import(value.clone(), Some(current))?,
+                    None => {
+                        let current = <#inner>::read(Some(globle.get(format!("{}{}", prefix, #key))?))?;
+                        <#inner>::import(value.clone(), Some(&current))?
                     }
-                }
+                };
+                self.#ident = Some(item);
             }
+            None if replace => self.#ident = #create,
+            None => {}
         }
-    }
-    panic!("modpack_setting derive macro can only be used on all Option<T> field")
+    })
 }
@@ -0,0 +1,145 @@
+//两个 derive 共用的 schema_version, 迁移和读取代码
+//parse 返回 crate::settings::migration::Preserved, 使用 derive 的 crate 需要提供这个路径
+pub fn expand(st: &syn::DeriveInput, keys: &[String]) -> syn::Result<proc_macro2::TokenStream> {
+    let migrations = match get_migrations(st)? {
+        Some(path) => quote::quote!(#path),
+        None => quote::quote!(&[]),
+    };
+    let rec = quote::quote! {
+        //没有 schema_version 的旧文件视为版本 1, 第 i 个迁移把版本 i + 1 升级到 i + 2
+        pub const SCHEMA_VERSION: u32 = 1 + Self::MIGRATIONS.len() as u32;
+        const MIGRATIONS: &'static [fn(&mut serde_json::Map<String, serde_json::Value>) -> anyhow::Result<()>] = #migrations;
+
+        pub fn schema_version(json: &serde_json::Value) -> anyhow::Result<u32> {
+            match json.get("schema_version") {
+                None => Ok(1),
+                Some(version) => match version.as_u64() {
+                    Some(version) if version >= 1 && version <= u32::MAX as u64 => Ok(version as u32),
+                    _ => Err(anyhow::anyhow!("Invalid schema_version: {}", version)),
+                },
+            }
+        }
+
+        //返回迁移前的版本, 不需要迁移时返回 None
+        //比当前更新的文件保持原样, 降级后也能读取认识的字段
+        pub fn migrate(json: &mut serde_json::Value) -> anyhow::Result<Option<u32>> {
+            let version = Self::schema_version(json)?;
+            if version >= Self::SCHEMA_VERSION {
+                return Ok(None);
+            }
+            let object = json
+                .as_object_mut()
+                .ok_or_else(|| anyhow::anyhow!("Setting file must be a json object"))?;
+            for migration in &Self::MIGRATIONS[(version - 1) as usize..] {
+                migration(object)?;
+            }
+            object.insert("schema_version".to_string(), serde_json::json!(Self::SCHEMA_VERSION));
+            Ok(Some(version))
+        }
+
+        //读取设置文件, 返回设置, 需要保留的内容和迁移前的版本
+        pub fn parse(content: &str) -> anyhow::Result<(Self, crate::settings::migration::Preserved, Option<u32>)> {
+            let mut json: serde_json::Value = serde_json::from_str(content)?;
+            let migrated_from = Self::migrate(&mut json)?;
+            let preserved = crate::settings::migration::Preserved {
+                schema_version: Self::schema_version(&json)?,
+                extra: Self::unknown_fields(&json),
+            };
+            Ok((Self::read(json)?, preserved, migrated_from))
+        }
+
+        //当前版本不认识的字段, 写回时保留
+        pub fn unknown_fields(json: &serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+            let mut fields = serde_json::Map::new();
+            if let Some(object) = json.as_object() {
+                for (key, value) in object {
+                    match key.as_str() {
+                        "schema_version" #(| #keys)* => {}
+                        _ => {
+                            fields.insert(key.clone(), value.clone());
+                        }
+                    }
+                }
+            }
+            fields
+        }
+    };
+    syn::Result::Ok(rec)
+}
+
+//#[setting(migrations = PATH)]
+fn get_migrations(st: &syn::DeriveInput) -> syn::Result<Option<syn::Path>> {
+    let mut migrations = None;
+    for attr in &st.attrs {
+        if !attr.path().is_ident("setting") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("migrations") {
+                migrations = Some(meta.value()?.parse::<syn::Path>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `migrations = ...`"))
+            }
+        })?;
+    }
+    syn::Result::Ok(migrations)
+}
+
+//生成 schema_items() 中的一项, 隐藏和跳过的字段不出现在描述中
+pub fn schema_item_field(field: &crate::attrs::SettingField) -> proc_macro2::TokenStream {
+    let ty = field.ty;
+    let key = &field.key;
+    if field.skip || field.hidden {
+        return quote::quote!();
+    }
+    if field.group {
+        return quote::quote! {
+            items.extend(<#ty>::schema_items(&format!("{}{}.", prefix, #key), overridable)?);
+        };
+    }
+    let label = match &field.label {
+        Some(label) => quote::quote!(#label.to_string()),
+        None => quote::quote!(format!("setting.{}.label", name)),
+    };
+    let description = match &field.description {
+        Some(description) => quote::quote!(#description.to_string()),
+        None => quote::quote!(format!("setting.{}.description", name)),
+    };
+    let default = match &field.default {
+        Some(default) => quote::quote! {{
+            let value: #ty = #default;
+            Some(value.write()?)
+        }},
+        None => quote::quote!(schema_default),
+    };
+    let readonly = field.readonly;
+    quote::quote! {{
+        let name = format!("{}{}", prefix, #key);
+        let mut constraints = match <#ty>::schema() {
+            serde_json::Value::Object(object) => object,
+            other => {
+                let mut object = serde_json::Map::new();
+                object.insert("type".to_string(), other);
+                object
+            }
+        };
+        let value_type = constraints
+            .remove("type")
+            .unwrap_or_else(|| serde_json::json!("object"));
+        let schema_default = constraints.remove("default");
+        let default: Option<serde_json::Value> = #default;
+        let label: String = #label;
+        let description: String = #description;
+        items.push(serde_json::json!({
+            "name": name,
+            "type": value_type,
+            "label": label,
+            "description": description,
+            "constraints": constraints,
+            "default": default,
+            "readonly": #readonly,
+            "overridable": overridable.contains(&name),
+        }));
+    }}
+}
@@ -1,5 +1,7 @@
 use proc_macro::TokenStream;
 
+use crate::attrs::{SettingField, get_fields};
+
 pub fn setting(input: TokenStream) -> TokenStream {
     let st = syn::parse_macro_input!(input as syn::DeriveInput);
     match expond(&st) {
@@ -11,40 +13,128 @@ pub fn setting(input: TokenStream) -> TokenStream {
 fn expond(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let struct_name = &st.ident;
     let struct_fields = get_fields(st)?;
-    let fields_name_vec: Vec<_> = struct_fields.iter().map(|f| &f.ident).collect();
+    let fields_name_vec: Vec<_> = struct_fields.iter().map(|f| f.ident).collect();
+    let keys: Vec<_> = struct_fields
+        .iter()
+        .filter(|f| !f.skip)
+        .map(|f| f.key.clone())
+        .collect();
+    let schema = crate::schema::expand(st, &keys)?;
+
+    let read_vec: Vec<_> = struct_fields.iter().map(read_field).collect();
+    let create_vec: Vec<_> = struct_fields.iter().map(create_field).collect();
+    let save_vec: Vec<_> = struct_fields.iter().map(save_field).collect();
+    let items_vec: Vec<_> = struct_fields
+        .iter()
+        .map(crate::attrs::items_field)
+        .collect();
+    let schema_item_vec: Vec<_> = struct_fields
+        .iter()
+        .map(crate::schema::schema_item_field)
+        .collect();
 
-    let fields_type_vec: Vec<_> = struct_fields.iter().map(|f| &f.ty).collect();
+    let leaf: Vec<_> = struct_fields
+        .iter()
+        .filter(|f| !f.skip && !f.group)
+        .collect();
+    let leaf_name_vec: Vec<_> = leaf.iter().map(|f| f.ident).collect();
+    let leaf_key_vec: Vec<_> = leaf.iter().map(|f| &f.key).collect();
+    let leaf_change_vec: Vec<_> = leaf
+        .iter()
+        .map(|f| {
+            let ident = f.ident;
+            if f.readonly {
+                quote::quote!(Err(anyhow::anyhow!("Item is readonly: {}", item_name)))
+            } else {
+                quote::quote! {{
+                    self.#ident.receive(value)?;
+                    Ok(())
+                }}
+            }
+        })
+        .collect();
+    let group: Vec<_> = struct_fields.iter().filter(|f| f.group).collect();
+    let group_name_vec: Vec<_> = group.iter().map(|f| f.ident).collect();
+    let group_key_vec: Vec<_> = group.iter().map(|f| &f.key).collect();
+
+    let item_readonly = crate::attrs::item_readonly_fn(&struct_fields);
+    let export_vec: Vec<_> = struct_fields.iter().map(export_field).collect();
+    let import_vec: Vec<_> = struct_fields.iter().map(import_field).collect();
 
     let rec = quote::quote! {
         impl #struct_name{
+            #schema
+            #item_readonly
+
             pub fn read(json: serde_json::Value) -> anyhow::Result<Self>{
-                #(let #fields_name_vec = #fields_type_vec::read(json.get(stringify!(#fields_name_vec)).cloned())?;)*
+                #(let #fields_name_vec = #read_vec;)*
                 Ok(Self {
                     #(#fields_name_vec,)*
                 })
             }
             pub fn create() -> anyhow::Result<Self> {
                 Ok(Self {
-                    #(#fields_name_vec: #fields_type_vec::read(None)?,)*
+                    #(#fields_name_vec: #create_vec,)*
                 })
             }
             pub fn save(&self) -> anyhow::Result<serde_json::Value> {
+                let mut json_data: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
+                json_data.insert("schema_version".to_string(), serde_json::json!(Self::SCHEMA_VERSION));
+                #(#save_vec)*
+                Ok(serde_json::Value::Object(json_data))
+            }
+            //导出到配置文件, 只读和跳过的设置项不导出
+            pub fn export(&self) -> anyhow::Result<serde_json::Value> {
+                let mut json_data: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
+                #(#export_vec)*
+                Ok(serde_json::Value::Object(json_data))
+            }
+            //从配置文件导入, replace 为 true 时配置文件中没有的设置项恢复默认值
+            //出错时可能已经导入了一部分, 调用者应当在副本上导入
+            pub fn import(&mut self, profile: &serde_json::Value, replace: bool) -> anyhow::Result<()> {
+                #(#import_vec)*
+                Ok(())
+            }
+            //前端可以看到的设置项, 分组中的设置项为 `group.item`
+            pub fn items() -> Vec<String> {
+                let mut items = Vec::new();
+                #(#items_vec)*
+                items
+            }
+            //所有设置项的描述, overridable 为整合包可以覆盖的设置项
+            pub fn schema(overridable: &[String]) -> anyhow::Result<serde_json::Value> {
                 Ok(serde_json::json!({
-                    #(stringify!(#fields_name_vec): self.#fields_name_vec.write()?,)*
+                    "schema_version": Self::SCHEMA_VERSION,
+                    "items": Self::schema_items("", overridable)?,
                 }))
             }
+            #[doc(hidden)]
+            pub fn schema_items(prefix: &str, overridable: &[String]) -> anyhow::Result<Vec<serde_json::Value>> {
+                let mut items = Vec::new();
+                #(#schema_item_vec)*
+                Ok(items)
+            }
             pub fn get(&self, item_name: String) -> anyhow::Result<serde_json::Value> {
+                if let Some((group, rest)) = item_name.split_once('.') {
+                    return match group {
+                        #(#group_key_vec => self.#group_name_vec.get(rest.to_string()),)*
+                        _ => Err(anyhow::anyhow!("Item not found")),
+                    };
+                }
                 match item_name.as_str() {
-                    #(stringify!(#fields_name_vec) => self.#fields_name_vec.send(),)*
+                    #(#leaf_key_vec => self.#leaf_name_vec.send(),)*
                     _ => Err(anyhow::anyhow!("Item not found")),
                 }
             }
-            pub fn change(&mut self, item_name: String, value: Vec<String>) -> anyhow::Result<()> {
+            pub fn change(&mut self, item_name: String, value: serde_json::Value) -> anyhow::Result<()> {
+                if let Some((group, rest)) = item_name.split_once('.') {
+                    return match group {
+                        #(#group_key_vec => self.#group_name_vec.change(rest.to_string(), value),)*
+                        _ => Err(anyhow::anyhow!("Item not found")),
+                    };
+                }
                 match item_name.as_str() {
-                    #(stringify!(#fields_name_vec) => {
-                        self.#fields_name_vec.receive(value)?;
-                        Ok(())
-                    })*
+                    #(#leaf_key_vec => #leaf_change_vec,)*
                     _ => Err(anyhow::anyhow!("Item not found")),
                 }
             }
@@ -53,19 +143,90 @@ fn expond(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     syn::Result::Ok(rec)
 }
 
-fn get_fields(
-    st: &syn::DeriveInput,
-) -> syn::Result<&syn::punctuated::Punctuated<syn::Field, syn::Token![,]>> {
-    if let syn::Data::Struct(syn::DataStruct {
-        fields: syn::Fields::Named(syn::FieldsNamed { ref named, .. }),
-        ..
-    }) = st.data
-    {
-        syn::Result::Ok(named)
+fn read_field(field: &SettingField) -> proc_macro2::TokenStream {
+    let ty = field.ty;
+    let key = &field.key;
+    if field.skip {
+        return create_field(field);
+    }
+    if field.group {
+        return quote::quote! {
+            <#ty>::read(json.get(#key).cloned().unwrap_or_else(|| serde_json::json!({})))?
+        };
+    }
+    match &field.default {
+        Some(default) => quote::quote! {
+            match json.get(#key) {
+                Some(value) => <#ty>::read(Some(value.clone()))?,
+                None => #default,
+            }
+        },
+        None => quote::quote!(<#ty>::read(json.get(#key).cloned())?),
+    }
+}
+
+fn create_field(field: &SettingField) -> proc_macro2::TokenStream {
+    let ty = field.ty;
+    match &field.default {
+        Some(default) => quote::quote!(#default),
+        None if field.skip => quote::quote!(::core::default::Default::default()),
+        None if field.group => quote::quote!(<#ty>::create()?),
+        None => quote::quote!(<#ty>::read(None)?),
+    }
+}
+
+fn save_field(field: &SettingField) -> proc_macro2::TokenStream {
+    let ident = field.ident;
+    let key = &field.key;
+    if field.skip {
+        quote::quote!()
+    } else if field.group {
+        quote::quote! {
+            let mut group_value = self.#ident.save()?;
+            if let Some(group) = group_value.as_object_mut() {
+                group.remove("schema_version");
+            }
+            json_data.insert(#key.to_string(), group_value);
+        }
     } else {
-        syn::Result::Err(syn::Error::new_spanned(
-            st,
-            "Setting derive macro can only be used on structs with named fields",
-        ))
+        quote::quote! {
+            json_data.insert(#key.to_string(), self.#ident.write()?);
+        }
+    }
+}
+
+fn export_field(field: &SettingField) -> proc_macro2::TokenStream {
+    let ident = field.ident;
+    let key = &field.key;
+    //分组的 export 同样返回对象
+    if field.skip || field.readonly {
+        quote::quote!()
+    } else {
+        quote::quote! {
+            json_data.insert(#key.to_string(), self.#ident.export()?);
+        }
+    }
+}
+
+fn import_field(field: &SettingField) -> proc_macro2::TokenStream {
+    let ident = field.ident;
+    let ty = field.ty;
+    let key = &field.key;
+    if field.skip || field.readonly {
+        return quote::quote!();
+    }
+    if field.group {
+        return quote::quote! {
+            self.#ident.import(profile.get(#key).unwrap_or(&serde_json::Value::Null), replace)?;
+        };
+    }
+    let create = create_field(field);
+    //和机器相关的设置项保持当前值, 默认值需要读取本机的配置
+    quote::quote! {
+        match profile.get(#key) {
+            Some(value) => self.#ident = <#ty>::import(value.clone(), Some(&self.#ident))?,
+            None if replace && !<#ty>::machine_specific() => self.#ident = #create,
+            None => {}
+        }
     }
 }
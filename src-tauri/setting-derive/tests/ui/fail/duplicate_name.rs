@@ -0,0 +1,10 @@
+use setting_derive::Setting;
+
+#[derive(Setting)]
+struct Settings {
+    java: String,
+    #[setting(rename = "java")]
+    runtime: String,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use setting_derive::Setting;
+
+#[derive(Setting)]
+struct Window {}
+
+#[derive(Setting)]
+struct Settings {
+    #[setting(group, default = Window {})]
+    window: Window,
+}
+
+fn main() {}
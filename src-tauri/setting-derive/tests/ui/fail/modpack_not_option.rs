@@ -0,0 +1,10 @@
+use setting_derive::ModpackSetting;
+
+struct Settings;
+
+#[derive(ModpackSetting)]
+struct ModpackSetting {
+    memory: i64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use setting_derive::Setting;
+
+#[derive(Setting)]
+struct Settings {
+    #[setting(skip, readonly)]
+    cache: Vec<String>,
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use setting_derive::Setting;
+
+#[derive(Setting)]
+struct Settings(String);
+
+fn main() {}
@@ -0,0 +1,104 @@
+include!("../support.rs");
+
+use serde_json::json;
+use setting_derive::{ModpackSetting, Setting};
+
+#[derive(Setting)]
+struct Window {
+    width: Number,
+}
+
+#[derive(Setting)]
+struct Settings {
+    memory: Number,
+    build: Number,
+    #[setting(group)]
+    window: Window,
+}
+
+#[derive(ModpackSetting)]
+struct WindowOverride {
+    width: Option<Number>,
+}
+
+#[derive(ModpackSetting)]
+struct ModpackSetting {
+    #[setting(default = Some(Number(1024)))]
+    memory: Option<Number>,
+    #[setting(readonly)]
+    build: Option<Number>,
+    #[setting(skip)]
+    loaded: bool,
+    #[setting(group)]
+    window: WindowOverride,
+}
+
+fn main() {
+    let globle = Settings::read(json!({
+        "memory": 2048,
+        "build": 7,
+        "window": {"width": 800},
+    }))
+    .unwrap();
+
+    let mut setting = ModpackSetting::create().unwrap();
+    assert!(!setting.loaded);
+    assert_eq!(setting.get("memory".to_string(), &globle).unwrap(), 1024);
+    assert_eq!(setting.get("build".to_string(), &globle).unwrap(), 7);
+    assert_eq!(setting.get("window.width".to_string(), &globle).unwrap(), 800);
+    assert_eq!(setting.save().unwrap(), json!({"schema_version": 1, "memory": 1024}));
+
+    setting
+        .change("window.width".to_string(), json!(1280), &globle)
+        .unwrap();
+    assert!(setting
+        .change("build".to_string(), json!(8), &globle)
+        .is_err());
+    assert_eq!(setting.get("window.width".to_string(), &globle).unwrap(), 1280);
+
+    let setting = ModpackSetting::read(setting.save().unwrap()).unwrap();
+    assert_eq!(setting.window.width, Some(Number(1280)));
+    assert_eq!(
+        ModpackSetting::items(),
+        vec!["memory", "build", "window.width"]
+    );
+
+    let mut setting = setting;
+    assert_eq!(setting.is_overridden("memory"), Some(true));
+    assert_eq!(setting.is_overridden("window.width"), Some(true));
+    assert_eq!(setting.is_overridden("loaded"), None);
+    setting.reset("window").unwrap();
+    assert_eq!(setting.is_overridden("window.width"), Some(false));
+    assert_eq!(setting.get("window.width".to_string(), &globle).unwrap(), 800);
+    assert!(setting.reset("unknown").is_err());
+
+    setting.build = Some(Number(9));
+    setting.reset_all();
+    assert_eq!(setting.is_overridden("memory"), Some(false));
+    //只读的设置项不会被 reset_all 清除
+    assert_eq!(setting.is_overridden("build"), Some(true));
+    //reset 和 change 返回相同的错误
+    let reset_err = setting.reset("build").unwrap_err().to_string();
+    let change_err = setting
+        .change("build".to_string(), json!(8), &globle)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(reset_err, "Item is readonly: build");
+    assert_eq!(reset_err, change_err);
+    assert_eq!(setting.build, Some(Number(9)));
+    setting.build = None;
+    assert_eq!(setting.save().unwrap(), json!({"schema_version": 1}));
+
+    assert_eq!(setting.export().unwrap(), json!({}));
+    setting.window.width = Some(Number(1280));
+    assert_eq!(setting.export().unwrap(), json!({"window": {"width": 1280}}));
+    setting
+        .import(&json!({"memory": 512, "build": 1}), false, &globle)
+        .unwrap();
+    assert_eq!(setting.memory, Some(Number(512)));
+    assert_eq!(setting.build, None);
+    assert_eq!(setting.window.width, Some(Number(1280)));
+    setting.import(&json!({"memory": 256}), true, &globle).unwrap();
+    assert_eq!(setting.memory, Some(Number(256)));
+    assert_eq!(setting.window.width, None);
+}
@@ -0,0 +1,121 @@
+include!("../support.rs");
+
+use serde_json::json;
+use setting_derive::Setting;
+
+#[derive(Setting)]
+struct Window {
+    width: Number,
+    #[setting(default = Number(600))]
+    height: Number,
+}
+
+#[derive(Setting)]
+struct Settings {
+    #[setting(rename = "max-memory", default = Number(2048))]
+    memory: Number,
+    #[setting(skip)]
+    cache: Vec<String>,
+    #[setting(hidden)]
+    launch_count: Number,
+    #[setting(readonly, label = "about.build")]
+    build: Number,
+    #[setting(group)]
+    window: Window,
+}
+
+fn main() {
+    let setting = Settings::create().unwrap();
+    assert_eq!(setting.memory, Number(2048));
+    assert!(setting.cache.is_empty());
+    assert_eq!(setting.window.height, Number(600));
+    assert_eq!(
+        Settings::items(),
+        vec!["max-memory", "build", "window.width", "window.height"]
+    );
+
+    let mut setting = Settings::read(json!({
+        "max-memory": 4096,
+        "cache": ["ignored"],
+        "launch_count": 3,
+        "window": {"width": 800},
+    }))
+    .unwrap();
+    assert!(setting.cache.is_empty());
+    assert_eq!(setting.get("max-memory".to_string()).unwrap(), 4096);
+    assert!(setting.get("memory".to_string()).is_err());
+    assert_eq!(setting.get("launch_count".to_string()).unwrap(), 3);
+    assert_eq!(setting.get("window.width".to_string()).unwrap(), 800);
+    assert_eq!(setting.get("window.height".to_string()).unwrap(), 600);
+    assert!(setting.get("window".to_string()).is_err());
+    assert!(setting.get("cache".to_string()).is_err());
+
+    setting
+        .change("window.height".to_string(), json!(720))
+        .unwrap();
+    assert!(setting.change("build".to_string(), json!(1)).is_err());
+    assert!(setting
+        .change("window.height".to_string(), json!("720"))
+        .is_err());
+    assert_eq!(Settings::item_readonly("build"), Some(true));
+    assert_eq!(Settings::item_readonly("launch_count"), Some(false));
+    assert_eq!(Settings::item_readonly("window.width"), Some(false));
+    assert_eq!(Settings::item_readonly("cache"), None);
+    assert_eq!(Settings::item_readonly("memory"), None);
+    assert_eq!(
+        setting.save().unwrap(),
+        json!({
+            "schema_version": 1,
+            "max-memory": 4096,
+            "launch_count": 3,
+            "build": 0,
+            "window": {"width": 800, "height": 720},
+        })
+    );
+    assert_eq!(
+        setting.export().unwrap(),
+        json!({
+            "max-memory": 4096,
+            "launch_count": 3,
+            "window": {"width": 800, "height": 720},
+        })
+    );
+    let mut imported = Settings::create().unwrap();
+    imported
+        .import(&json!({"window": {"width": 1024}}), false)
+        .unwrap();
+    assert_eq!(imported.window.width, Number(1024));
+    assert_eq!(imported.window.height, Number(600));
+    imported.import(&json!({"build": 5}), true).unwrap();
+    assert_eq!(imported.build, Number(0));
+    assert_eq!(imported.window.width, Number(0));
+    assert_eq!(imported.memory, Number(2048));
+
+    assert_eq!(
+        Settings::unknown_fields(&json!({"cache": 1, "build": 2})),
+        json!({"cache": 1}).as_object().unwrap().clone()
+    );
+
+    let schema = Settings::schema(&["max-memory".to_string()]).unwrap();
+    assert_eq!(schema["schema_version"], 1);
+    assert_eq!(
+        schema["items"][0],
+        json!({
+            "name": "max-memory",
+            "type": "integer",
+            "label": "setting.max-memory.label",
+            "description": "setting.max-memory.description",
+            "constraints": {"minimum": 0},
+            "default": 2048,
+            "readonly": false,
+            "overridable": true,
+        })
+    );
+    assert_eq!(schema["items"][1]["label"], "about.build");
+    assert_eq!(schema["items"][1]["readonly"], true);
+    assert_eq!(schema["items"][1]["overridable"], false);
+    assert_eq!(schema["items"][1]["default"], 0);
+    assert_eq!(schema["items"][2]["name"], "window.width");
+    assert_eq!(schema["items"][3]["default"], 600);
+    assert_eq!(schema["items"].as_array().unwrap().len(), 4);
+}
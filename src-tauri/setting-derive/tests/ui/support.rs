@@ -0,0 +1,69 @@
+// UI 测试共用的设置项类型, 和 src-tauri 中的 SettingTrait 保持一致
+use anyhow::Result;
+use serde_json::Value;
+
+pub trait SettingTrait: Sized {
+    fn read(json: Option<Value>) -> Result<Self>;
+    fn write(&self) -> Result<Value>;
+    fn send(&self) -> Result<Value>;
+    fn receive(&mut self, value: Value) -> Result<()>;
+    fn schema() -> Value {
+        serde_json::json!({ "type": "object" })
+    }
+    fn export(&self) -> Result<Value> {
+        self.write()
+    }
+    fn import(value: Value, current: Option<&Self>) -> Result<Self> {
+        let _ = current;
+        Self::read(Some(value))
+    }
+    fn machine_specific() -> bool {
+        false
+    }
+
+    fn read_modpack(json: Option<Value>) -> Result<Option<Self>> {
+        match json {
+            Some(value) => Ok(Some(Self::read(Some(value))?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Number(pub i64);
+
+impl SettingTrait for Number {
+    fn read(json: Option<Value>) -> Result<Self> {
+        Ok(Number(json.and_then(|v| v.as_i64()).unwrap_or(0)))
+    }
+
+    fn write(&self) -> Result<Value> {
+        Ok(self.0.into())
+    }
+
+    fn send(&self) -> Result<Value> {
+        Ok(self.0.into())
+    }
+
+    fn receive(&mut self, value: Value) -> Result<()> {
+        self.0 = value
+            .as_i64()
+            .ok_or_else(|| anyhow::anyhow!("expected an integer"))?;
+        Ok(())
+    }
+
+    fn schema() -> Value {
+        serde_json::json!({ "type": "integer", "minimum": 0, "default": 0 })
+    }
+}
+
+//生成的 parse 使用 crate::settings::migration::Preserved, 路径和字段与 src-tauri 保持一致
+pub mod settings {
+    pub mod migration {
+        #[derive(Default)]
+        pub struct Preserved {
+            pub schema_version: u32,
+            pub extra: serde_json::Map<String, serde_json::Value>,
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+use super::{
+    rules::{Environment, OsName},
+    version::Version,
+    version_json::{Library, VersionJson},
+};
+
+//maven 坐标 group:artifact:version[:classifier][@extension]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MavenCoordinate {
+    pub group: String,
+    pub artifact: String,
+    pub version: String,
+    pub classifier: Option<String>,
+    pub extension: String,
+}
+
+impl MavenCoordinate {
+    pub fn parse(name: &str) -> Result<Self> {
+        let (coordinate, extension) = match name.split_once('@') {
+            Some((coordinate, extension)) => (coordinate, extension),
+            None => (name, "jar"),
+        };
+        let parts: Vec<&str> = coordinate.split(':').collect();
+        if parts.len() < 3 || parts.len() > 4 || parts.iter().any(|p| p.is_empty()) {
+            bail!("Invalid maven coordinate `{}`", name);
+        }
+        Ok(MavenCoordinate {
+            group: parts[0].to_string(),
+            artifact: parts[1].to_string(),
+            version: parts[2].to_string(),
+            classifier: parts.get(3).map(|c| c.to_string()),
+            extension: extension.to_string(),
+        })
+    }
+
+    pub fn with_classifier(&self, classifier: &str) -> Self {
+        MavenCoordinate {
+            classifier: Some(classifier.to_string()),
+            ..self.clone()
+        }
+    }
+
+    //相对于 libraries 目录的路径, 使用 / 分隔
+    pub fn path(&self) -> String {
+        let classifier = self
+            .classifier
+            .as_ref()
+            .map(|c| format!("-{}", c))
+            .unwrap_or_default();
+        format!(
+            "{}/{}/{}/{}-{}{}.{}",
+            self.group.replace('.', "/"),
+            self.artifact,
+            self.version,
+            self.artifact,
+            self.version,
+            classifier,
+            self.extension
+        )
+    }
+
+    //同一个库的不同版本 key 相同, 带 natives 的同名库(如 1.12.2 的 text2speech)单独计算
+    fn key(&self, natives: bool) -> (String, String, Option<String>, bool) {
+        (
+            self.group.clone(),
+            self.artifact.clone(),
+            self.classifier.clone(),
+            natives,
+        )
+    }
+}
+
+//需要解压的旧版本本地库
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NativeJar {
+    pub path: PathBuf,
+    pub exclude: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Classpath {
+    pub entries: Vec<PathBuf>,
+    pub natives: Vec<NativeJar>,
+    separator: &'static str,
+}
+
+impl Classpath {
+    //libraries 已经按规则过滤, 游戏 jar 放在最后
+    pub fn build(
+        version: &VersionJson,
+        env: &Environment,
+        libraries_dir: &Path,
+        client_jar: &Path,
+    ) -> Result<Self> {
+        let mut entries = Vec::new();
+        let mut natives = Vec::new();
+        for library in dedupe(env.libraries(version))? {
+            let coordinate = MavenCoordinate::parse(&library.name)?;
+            //带 natives 的库也可能有 artifact, 和同名的普通库指向同一个 jar
+            let has_artifact = library
+                .downloads
+                .as_ref()
+                .is_some_and(|d| d.artifact.is_some());
+            let classifier = env.native_classifier(library);
+            if classifier.is_none() || has_artifact {
+                let path = libraries_dir.join(library_path(library, &coordinate));
+                if !entries.contains(&path) {
+                    entries.push(path);
+                }
+            }
+            if let Some(classifier) = classifier {
+                let artifact = library
+                    .downloads
+                    .as_ref()
+                    .and_then(|d| d.classifiers.get(&classifier));
+                let path = match artifact.and_then(|a| a.path.clone()) {
+                    Some(path) => path,
+                    None => coordinate.with_classifier(&classifier).path(),
+                };
+                natives.push(NativeJar {
+                    path: libraries_dir.join(path),
+                    exclude: library
+                        .extract
+                        .as_ref()
+                        .map(|e| e.exclude.clone())
+                        .unwrap_or_default(),
+                });
+            }
+        }
+        entries.push(client_jar.to_path_buf());
+        Ok(Classpath {
+            entries,
+            natives,
+            separator: classpath_separator(env.os),
+        })
+    }
+
+    pub fn separator(&self) -> &'static str {
+        self.separator
+    }
+
+    //把旧版本的本地库解压到 dir, 跳过 extract.exclude 中的前缀
+    pub fn extract_natives(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        for native in &self.natives {
+            extract_native(native, dir).map_err(|e| {
+                anyhow::anyhow!("Failed to extract `{}`: {}", native.path.display(), e)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Classpath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|path| path.to_string_lossy())
+            .collect();
+        write!(f, "{}", entries.join(self.separator))
+    }
+}
+
+pub fn classpath_separator(os: OsName) -> &'static str {
+    match os {
+        OsName::Windows => ";",
+        OsName::Osx | OsName::Linux => ":",
+    }
+}
+
+//优先使用 downloads.artifact.path, 加载器的库只有坐标
+fn library_path(library: &Library, coordinate: &MavenCoordinate) -> String {
+    library
+        .downloads
+        .as_ref()
+        .and_then(|d| d.artifact.as_ref())
+        .and_then(|a| a.path.clone())
+        .unwrap_or_else(|| coordinate.path())
+}
+
+//group/artifact/classifier 和是否带 natives 都相同的库只保留最新的版本, 位置使用第一次出现的位置
+//版本相同时保留先出现的, 合并后子版本的库在前
+pub fn dedupe(libraries: Vec<&Library>) -> Result<Vec<&Library>> {
+    let mut result: Vec<(&Library, MavenCoordinate)> = Vec::new();
+    let mut index = HashMap::new();
+    for library in libraries {
+        let coordinate = MavenCoordinate::parse(&library.name)?;
+        let key = coordinate.key(library.natives.is_some());
+        match index.get(&key) {
+            Some(&i) => {
+                let (_, existing): &(&Library, MavenCoordinate) = &result[i];
+                if newer(&coordinate.version, &existing.version) {
+                    result[i] = (library, coordinate);
+                }
+            }
+            None => {
+                index.insert(key, result.len());
+                result.push((library, coordinate));
+            }
+        }
+    }
+    Ok(result.into_iter().map(|(library, _)| library).collect())
+}
+
+//无法解析的版本号按字符串比较
+fn newer(a: &str, b: &str) -> bool {
+    match (Version::from_str(a), Version::from_str(b)) {
+        (Ok(a), Ok(b)) => a > b,
+        _ => a > b,
+    }
+}
+
+fn extract_native(native: &NativeJar, dir: &Path) -> Result<()> {
+    let mut zip = zip::ZipArchive::new(File::open(&native.path)?)?;
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        if entry.is_dir()
+            || native
+                .exclude
+                .iter()
+                .any(|prefix| entry.name().starts_with(prefix.as_str()))
+        {
+            continue;
+        }
+        let Some(relative) = entry.enclosed_name() else {
+            bail!("Unsafe path `{}`", entry.name());
+        };
+        let path = dir.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut entry, &mut File::create(&path)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::rules::Arch;
+    use std::io::Write;
+
+    fn fixture(id: &str) -> VersionJson {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/versions");
+        VersionJson::resolve(&dir, id).unwrap()
+    }
+
+    fn library(name: &str) -> Library {
+        serde_json::from_value(serde_json::json!({ "name": name })).unwrap()
+    }
+
+    #[test]
+    fn test_maven_coordinate() {
+        let coordinate = MavenCoordinate::parse("org.lwjgl:lwjgl:3.3.1:natives-linux").unwrap();
+        assert_eq!(coordinate.classifier.as_deref(), Some("natives-linux"));
+        assert_eq!(
+            coordinate.path(),
+            "org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar"
+        );
+        assert_eq!(
+            MavenCoordinate::parse("net.fabricmc:intermediary:1.20.1")
+                .unwrap()
+                .path(),
+            "net/fabricmc/intermediary/1.20.1/intermediary-1.20.1.jar"
+        );
+        assert_eq!(
+            MavenCoordinate::parse("de.oceanlabs.mcp:mcp_config:1.20.1@zip")
+                .unwrap()
+                .path(),
+            "de/oceanlabs/mcp/mcp_config/1.20.1/mcp_config-1.20.1.zip"
+        );
+        assert!(MavenCoordinate::parse("org.lwjgl:lwjgl").is_err());
+        assert!(MavenCoordinate::parse("a::1").is_err());
+    }
+
+    #[test]
+    fn test_dedupe() {
+        let libraries = [
+            library("org.ow2.asm:asm:9.3"),
+            library("org.lwjgl:lwjgl:3.3.1"),
+            library("org.ow2.asm:asm:9.6"),
+            library("org.lwjgl:lwjgl:3.3.1:natives-linux"),
+            library("org.ow2.asm:asm:9.10"),
+            library("org.lwjgl:lwjgl:3.2.2"),
+            serde_json::from_value(serde_json::json!({
+                "name": "org.ow2.asm:asm:9.10",
+                "natives": { "linux": "natives-linux" },
+            }))
+            .unwrap(),
+        ];
+        let deduped = dedupe(libraries.iter().collect()).unwrap();
+        let names: Vec<&str> = deduped.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "org.ow2.asm:asm:9.10",
+                "org.lwjgl:lwjgl:3.3.1",
+                "org.lwjgl:lwjgl:3.3.1:natives-linux",
+                "org.ow2.asm:asm:9.10",
+            ]
+        );
+        assert!(deduped[3].natives.is_some());
+    }
+
+    #[test]
+    fn test_build_classpath() {
+        let version = fixture("fabric-loader-0.15.11-1.20.1");
+        let libraries = Path::new("/game/libraries");
+        let client = Path::new("/game/versions/1.20.1/1.20.1.jar");
+        let linux = Environment::new(OsName::Linux, Arch::X86_64, "6.1.0");
+        let classpath = Classpath::build(&version, &linux, libraries, client).unwrap();
+        assert!(classpath.natives.is_empty());
+        assert_eq!(classpath.entries.len(), 12);
+        assert_eq!(
+            classpath.entries[0],
+            libraries.join("org/ow2/asm/asm/9.6/asm-9.6.jar")
+        );
+        assert_eq!(
+            classpath.entries[10],
+            libraries.join("org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar")
+        );
+        assert_eq!(classpath.entries[11], client);
+        let string = classpath.to_string();
+        assert!(string.starts_with("/game/libraries/org/ow2/asm/asm/9.6/asm-9.6.jar:"));
+        assert!(string.ends_with(":/game/versions/1.20.1/1.20.1.jar"));
+
+        let windows = Environment::new(OsName::Windows, Arch::X86_64, "10.0");
+        let classpath = Classpath::build(&version, &windows, libraries, client).unwrap();
+        assert_eq!(classpath.separator(), ";");
+        assert_eq!(classpath.to_string().matches(';').count(), 12);
+    }
+
+    #[test]
+    fn test_legacy_natives() {
+        let version = fixture("1.12.2-forge-14.23.5.2860");
+        let dir = tempfile::tempdir().unwrap();
+        let libraries = dir.path().join("libraries");
+        let client = dir.path().join("versions/1.12.2/1.12.2.jar");
+        let linux = Environment::new(OsName::Linux, Arch::X86_64, "6.1.0");
+        let classpath = Classpath::build(&version, &linux, &libraries, &client).unwrap();
+        //guava 和 text2speech 只保留一份, lwjgl-platform 不在 classpath 中
+        assert_eq!(classpath.entries.len(), 8);
+        assert_eq!(
+            classpath.entries[6],
+            libraries.join("com/mojang/text2speech/1.10.3/text2speech-1.10.3.jar")
+        );
+        assert_eq!(
+            classpath.natives,
+            vec![
+                NativeJar {
+                    path: libraries.join(
+                        "org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-linux.jar"
+                    ),
+                    exclude: vec!["META-INF/".to_string()],
+                },
+                NativeJar {
+                    path: libraries
+                        .join("com/mojang/text2speech/1.10.3/text2speech-1.10.3-natives-linux.jar"),
+                    exclude: vec!["META-INF/".to_string()],
+                },
+            ]
+        );
+
+        let native = &classpath.natives[0].path;
+        fs::create_dir_all(native.parent().unwrap()).unwrap();
+        let mut zip = zip::ZipWriter::new(File::create(native).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, data) in [
+            ("liblwjgl64.so", &b"lwjgl"[..]),
+            ("META-INF/MANIFEST.MF", b"manifest"),
+            ("linux/libopenal64.so", b"openal"),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.add_directory("empty/", options).unwrap();
+        zip.finish().unwrap();
+        let speech = &classpath.natives[1].path;
+        fs::create_dir_all(speech.parent().unwrap()).unwrap();
+        let mut zip = zip::ZipWriter::new(File::create(speech).unwrap());
+        zip.start_file("libtext2speech.so", options).unwrap();
+        zip.write_all(b"speech").unwrap();
+        zip.finish().unwrap();
+
+        let natives = dir.path().join("natives");
+        classpath.extract_natives(&natives).unwrap();
+        assert_eq!(fs::read(natives.join("liblwjgl64.so")).unwrap(), b"lwjgl");
+        assert_eq!(
+            fs::read(natives.join("linux/libopenal64.so")).unwrap(),
+            b"openal"
+        );
+        assert_eq!(
+            fs::read(natives.join("libtext2speech.so")).unwrap(),
+            b"speech"
+        );
+        assert!(!natives.join("META-INF").exists());
+        assert!(!natives.join("empty").exists());
+
+        //本地库缺失时返回错误
+        fs::remove_file(native).unwrap();
+        assert!(classpath.extract_natives(&natives).is_err());
+    }
+}
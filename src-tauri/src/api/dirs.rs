@@ -10,6 +10,16 @@ pub fn get_config_dirs() -> Result<PathBuf> {
     }
 }
 
+//启动器自己下载的java运行时
+pub fn get_runtime_dirs() -> Result<PathBuf> {
+    get_and_create_dir(get_config_dirs()?.join("runtime"))
+}
+
+//可以重新下载的缓存, 如版本清单
+pub fn get_cache_dirs() -> Result<PathBuf> {
+    get_and_create_dir(get_config_dirs()?.join("cache"))
+}
+
 fn get_and_create_dir(path: PathBuf) -> Result<PathBuf> {
     if !path.exists() {
         std::fs::create_dir_all(&path)?;
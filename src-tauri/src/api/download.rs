@@ -0,0 +1,165 @@
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Checksum {
+    Sha1(String),
+    Sha256(String),
+}
+
+impl Checksum {
+    pub fn verify(&self, data: &[u8]) -> Result<()> {
+        self.check(&mut &data[..])
+    }
+
+    pub fn verify_file(&self, path: &Path) -> Result<()> {
+        let mut file = File::open(path)?;
+        self.check(&mut file)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+    }
+
+    fn check(&self, reader: &mut impl Read) -> Result<()> {
+        let (expected, actual) = match self {
+            Checksum::Sha1(expected) => (expected, digest::<Sha1>(reader)?),
+            Checksum::Sha256(expected) => (expected, digest::<Sha256>(reader)?),
+        };
+        if !expected.eq_ignore_ascii_case(&actual) {
+            bail!(
+                "Checksum mismatch, expected {} but got {}",
+                expected,
+                actual
+            );
+        }
+        Ok(())
+    }
+}
+
+fn digest<D: Digest>(reader: &mut impl Read) -> Result<String> {
+    let mut hasher = D::new();
+    let mut buf = [0_u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub fn client() -> Result<Client> {
+    let client = Client::builder()
+        .user_agent(concat!("rev-launcher/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(15))
+        .build()?;
+    Ok(client)
+}
+
+pub fn get_bytes(client: &Client, url: &str) -> Result<Vec<u8>> {
+    let response = client.get(url).send()?.error_for_status()?;
+    Ok(response.bytes()?.to_vec())
+}
+
+pub fn get_json<T: DeserializeOwned>(client: &Client, url: &str) -> Result<T> {
+    let bytes = get_bytes(client, url)?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to parse json from `{}`: {}", url, e))
+}
+
+//先下载到 .part 文件, 校验通过后再改名, 避免留下不完整的文件
+pub fn download_file(
+    client: &Client,
+    url: &str,
+    path: &Path,
+    checksum: Option<&Checksum>,
+    size: Option<u64>,
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let part = part_path(path);
+    let mut response = client.get(url).send()?.error_for_status()?;
+    let mut file = File::create(&part)?;
+    let written = response.copy_to(&mut file)?;
+    file.flush()?;
+    drop(file);
+
+    let verified = verify_download(&part, written, checksum, size);
+    if let Err(e) = verified {
+        let _ = fs::remove_file(&part);
+        bail!("Failed to download `{}`: {}", url, e);
+    }
+    fs::rename(&part, path)?;
+    Ok(())
+}
+
+pub fn verify_download(
+    part: &Path,
+    written: u64,
+    checksum: Option<&Checksum>,
+    size: Option<u64>,
+) -> Result<()> {
+    if let Some(size) = size {
+        if written != size {
+            bail!("Size mismatch, expected {} bytes but got {}", size, written);
+        }
+    }
+    if let Some(checksum) = checksum {
+        checksum.verify_file(part)?;
+    }
+    Ok(())
+}
+
+pub fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_server::TestServer;
+
+    #[test]
+    fn test_checksum() {
+        let sha1 = Checksum::Sha1("a9993e364706816aba3e25717850c26c9cd0d89d".to_string());
+        assert!(sha1.verify(b"abc").is_ok());
+        assert!(sha1.verify(b"abd").is_err());
+        let sha256 = Checksum::Sha256(
+            "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD".to_string(),
+        );
+        assert!(sha256.verify(b"abc").is_ok());
+    }
+
+    #[test]
+    fn test_download_file() {
+        let server = TestServer::start();
+        server.set("/file", b"abc".to_vec());
+        let dir = tempfile::tempdir().unwrap();
+        let client = client().unwrap();
+
+        let path = dir.path().join("sub").join("file");
+        let sha1 = Checksum::Sha1("a9993e364706816aba3e25717850c26c9cd0d89d".to_string());
+        download_file(&client, &server.url("/file"), &path, Some(&sha1), Some(3)).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"abc");
+
+        let bad = dir.path().join("bad");
+        let wrong = Checksum::Sha1("0000000000000000000000000000000000000000".to_string());
+        assert!(download_file(&client, &server.url("/file"), &bad, Some(&wrong), None).is_err());
+        assert!(download_file(&client, &server.url("/file"), &bad, None, Some(4)).is_err());
+        assert!(download_file(&client, &server.url("/missing"), &bad, None, None).is_err());
+        assert!(!bad.exists());
+        assert!(!part_path(&bad).exists());
+    }
+}
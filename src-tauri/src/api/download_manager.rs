@@ -0,0 +1,642 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use reqwest::{
+    blocking::Client,
+    header::{CONTENT_RANGE, RANGE},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+
+use super::download::{self, part_path, verify_download, Checksum};
+
+//下载进度发送给前端的事件
+pub const DOWNLOAD_PROGRESS_EVENT: &str = "download-progress";
+
+const DEFAULT_CONCURRENCY: usize = 8;
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+//两次进度回调的最小间隔, 文件完成时总是回调
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DownloadTask {
+    pub url: String,
+    pub path: PathBuf,
+    pub sha1: Option<String>,
+    pub size: Option<u64>,
+}
+
+impl DownloadTask {
+    fn checksum(&self) -> Option<Checksum> {
+        self.sha1.clone().map(Checksum::Sha1)
+    }
+
+    //大小和 sha1 都对得上的文件不需要再下载, 两个都没有时只要文件存在
+    fn is_valid(&self) -> bool {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return false;
+        };
+        metadata.is_file()
+            && self.size.is_none_or(|size| size == metadata.len())
+            && self
+                .checksum()
+                .is_none_or(|checksum| checksum.verify_file(&self.path).is_ok())
+    }
+}
+
+//所有任务合计的进度, 跳过的文件也算在已完成和已下载的字节里
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct DownloadProgress {
+    pub total_files: usize,
+    pub finished_files: usize,
+    pub skipped_files: usize,
+    pub failed_files: usize,
+    //只统计知道大小的文件
+    pub total_bytes: u64,
+    pub downloaded_bytes: u64,
+}
+
+//前端可能同时有多个下载, 用 job 区分
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadEvent {
+    pub job: String,
+    #[serde(flatten)]
+    pub progress: DownloadProgress,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+pub struct DownloadManager {
+    client: Client,
+    concurrency: usize,
+    retries: u32,
+    //第 n 次重试前等待 backoff * 2^n, 最多 MAX_BACKOFF
+    backoff: Duration,
+}
+
+//一次尝试失败的原因, 网络错误和服务器错误可以重试
+struct Failure {
+    error: anyhow::Error,
+    retry: bool,
+}
+
+impl Failure {
+    fn retry(error: impl Into<anyhow::Error>) -> Self {
+        Failure {
+            error: error.into(),
+            retry: true,
+        }
+    }
+
+    fn fatal(error: impl Into<anyhow::Error>) -> Self {
+        Failure {
+            error: error.into(),
+            retry: false,
+        }
+    }
+}
+
+//工作线程共享的进度
+struct Shared<'a> {
+    state: Mutex<(DownloadProgress, Instant)>,
+    on_progress: &'a (dyn Fn(&DownloadProgress) + Sync),
+}
+
+impl Shared<'_> {
+    fn update(&self, force: bool, f: impl FnOnce(&mut DownloadProgress)) {
+        let snapshot = {
+            let mut state = self.state.lock().unwrap();
+            f(&mut state.0);
+            if !force && state.1.elapsed() < PROGRESS_INTERVAL {
+                return;
+            }
+            state.1 = Instant::now();
+            state.0.clone()
+        };
+        (self.on_progress)(&snapshot);
+    }
+
+    //counted 是这个文件已经计入 downloaded_bytes 的字节数
+    fn set_counted(&self, counted: &mut u64, value: u64) {
+        if *counted != value {
+            let old = *counted;
+            self.update(false, |p| {
+                p.downloaded_bytes = p.downloaded_bytes - old + value
+            });
+            *counted = value;
+        }
+    }
+}
+
+impl DownloadManager {
+    pub fn new() -> Result<Self> {
+        Ok(DownloadManager {
+            client: download::client()?,
+            concurrency: DEFAULT_CONCURRENCY,
+            retries: DEFAULT_RETRIES,
+            backoff: DEFAULT_BACKOFF,
+        })
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    //下载所有文件, 有文件失败时等其他文件下载完再返回错误
+    //取消后保留 .part 文件, 下次可以继续下载
+    pub fn run(
+        &self,
+        tasks: Vec<DownloadTask>,
+        cancel: &CancelToken,
+        on_progress: impl Fn(&DownloadProgress) + Sync,
+    ) -> Result<DownloadProgress> {
+        let tasks = dedupe(tasks)?;
+        let progress = DownloadProgress {
+            total_files: tasks.len(),
+            total_bytes: tasks.iter().filter_map(|task| task.size).sum(),
+            ..Default::default()
+        };
+        let shared = Shared {
+            state: Mutex::new((progress, Instant::now())),
+            on_progress: &on_progress,
+        };
+        let workers = self.concurrency.min(tasks.len());
+        let queue = Mutex::new(VecDeque::from(tasks));
+        let errors = Mutex::new(Vec::new());
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    let Some(task) = queue.lock().unwrap().pop_front() else {
+                        break;
+                    };
+                    match self.download(&task, cancel, &shared) {
+                        Ok(skipped) => shared.update(true, |p| {
+                            p.finished_files += 1;
+                            if skipped {
+                                p.skipped_files += 1;
+                            }
+                        }),
+                        //取消导致的失败不算
+                        Err(_) if cancel.is_cancelled() => break,
+                        Err(e) => {
+                            shared.update(true, |p| p.failed_files += 1);
+                            errors.lock().unwrap().push(format!("{}: {}", task.url, e));
+                        }
+                    }
+                });
+            }
+        });
+
+        let progress = shared.state.into_inner().unwrap().0;
+        on_progress(&progress);
+        if cancel.is_cancelled() {
+            bail!("Download cancelled");
+        }
+        let errors = errors.into_inner().unwrap();
+        if !errors.is_empty() {
+            bail!(
+                "{} of {} files failed to download:\n{}",
+                errors.len(),
+                progress.total_files,
+                errors.join("\n")
+            );
+        }
+        Ok(progress)
+    }
+
+    //返回文件是否已经有效而跳过
+    fn download(&self, task: &DownloadTask, cancel: &CancelToken, shared: &Shared) -> Result<bool> {
+        if task.is_valid() {
+            let size = task.size.unwrap_or_default();
+            shared.update(false, |p| p.downloaded_bytes += size);
+            return Ok(true);
+        }
+        if let Some(parent) = task.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut counted = 0;
+        let mut attempt = 0;
+        loop {
+            let failure = match self.fetch(task, cancel, shared, &mut counted) {
+                Ok(()) => return Ok(false),
+                Err(failure) => failure,
+            };
+            if !failure.retry || attempt >= self.retries || cancel.is_cancelled() {
+                shared.set_counted(&mut counted, 0);
+                return Err(failure.error);
+            }
+            let delay = self
+                .backoff
+                .saturating_mul(2_u32.saturating_pow(attempt))
+                .min(MAX_BACKOFF);
+            sleep(delay, cancel);
+            attempt += 1;
+        }
+    }
+
+    //有 .part 文件时用 Range 请求继续下载, 服务器不支持时从头下载
+    fn fetch(
+        &self,
+        task: &DownloadTask,
+        cancel: &CancelToken,
+        shared: &Shared,
+        counted: &mut u64,
+    ) -> Result<(), Failure> {
+        let part = part_path(&task.path);
+        let mut offset = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+        //.part 比文件还大, 只能重新下载
+        if task.size.is_some_and(|size| offset > size) {
+            offset = 0;
+        }
+
+        //.part 已经完整时不用再请求
+        if offset == 0 || task.size != Some(offset) {
+            let mut request = self.client.get(&task.url);
+            if offset > 0 {
+                request = request.header(RANGE, format!("bytes={}-", offset));
+            }
+            let mut response = request.send().map_err(|e| {
+                let retry = !e.is_builder();
+                Failure {
+                    error: e.into(),
+                    retry,
+                }
+            })?;
+            let status = response.status();
+            let resumed = format!("bytes {}-", offset);
+            let append = match status {
+                StatusCode::PARTIAL_CONTENT if offset > 0 => {
+                    let range = response.headers().get(CONTENT_RANGE);
+                    if !range
+                        .and_then(|r| r.to_str().ok())
+                        .is_some_and(|r| r.starts_with(&resumed))
+                    {
+                        let _ = fs::remove_file(&part);
+                        return Err(Failure::retry(anyhow::anyhow!(
+                            "Unexpected Content-Range {:?}",
+                            range
+                        )));
+                    }
+                    true
+                }
+                StatusCode::OK => false,
+                //.part 和服务器上的文件对不上
+                StatusCode::RANGE_NOT_SATISFIABLE => {
+                    let _ = fs::remove_file(&part);
+                    return Err(Failure::retry(anyhow::anyhow!("Range not satisfiable")));
+                }
+                status => {
+                    let retry = status.is_server_error()
+                        || status == StatusCode::REQUEST_TIMEOUT
+                        || status == StatusCode::TOO_MANY_REQUESTS;
+                    return Err(Failure {
+                        error: anyhow::anyhow!("HTTP status {}", status),
+                        retry,
+                    });
+                }
+            };
+
+            let mut file = if append {
+                OpenOptions::new().append(true).open(&part)
+            } else {
+                offset = 0;
+                File::create(&part)
+            }
+            .map_err(Failure::fatal)?;
+            shared.set_counted(counted, offset);
+            let mut buf = [0_u8; 64 * 1024];
+            loop {
+                if cancel.is_cancelled() {
+                    return Err(Failure::fatal(anyhow::anyhow!("Download cancelled")));
+                }
+                let n = response.read(&mut buf).map_err(Failure::retry)?;
+                if n == 0 {
+                    break;
+                }
+                file.write_all(&buf[..n]).map_err(Failure::fatal)?;
+                shared.set_counted(counted, *counted + n as u64);
+            }
+            file.flush().map_err(Failure::fatal)?;
+        }
+
+        let written = fs::metadata(&part).map_err(Failure::fatal)?.len();
+        shared.set_counted(counted, written);
+        if let Err(e) = verify_download(&part, written, task.checksum().as_ref(), task.size) {
+            let _ = fs::remove_file(&part);
+            shared.set_counted(counted, 0);
+            return Err(Failure::retry(e));
+        }
+        fs::rename(&part, &task.path).map_err(Failure::fatal)?;
+        Ok(())
+    }
+}
+
+//同一个路径只下载一次, 不然多个线程会同时写同一个 .part 文件
+//资源文件按 hash 存放, 不同的资源可能是同一个文件
+fn dedupe(tasks: Vec<DownloadTask>) -> Result<Vec<DownloadTask>> {
+    let mut result: Vec<DownloadTask> = Vec::new();
+    let mut index: HashMap<PathBuf, usize> = HashMap::new();
+    for task in tasks {
+        match index.get(&task.path) {
+            Some(&i) => {
+                let existing = &result[i];
+                let same_sha1 = match (&existing.sha1, &task.sha1) {
+                    (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+                    (a, b) => a == b,
+                };
+                if !same_sha1 || existing.size != task.size {
+                    bail!(
+                        "Conflicting downloads for `{}`: `{}` and `{}`",
+                        task.path.display(),
+                        existing.url,
+                        task.url
+                    );
+                }
+            }
+            None => {
+                index.insert(task.path.clone(), result.len());
+                result.push(task);
+            }
+        }
+    }
+    Ok(result)
+}
+
+//分段等待, 取消后尽快返回
+fn sleep(duration: Duration, cancel: &CancelToken) {
+    let deadline = Instant::now() + duration;
+    while !cancel.is_cancelled() {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        thread::sleep((deadline - now).min(Duration::from_millis(50)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_server::{Fault, TestServer};
+    use sha1::{Digest, Sha1};
+    use std::path::Path;
+
+    fn task(server: &TestServer, dir: &Path, name: &str, body: &[u8]) -> DownloadTask {
+        server.set(&format!("/{}", name), body.to_vec());
+        DownloadTask {
+            url: server.url(&format!("/{}", name)),
+            path: dir.join(name),
+            sha1: Some(hex::encode(Sha1::digest(body))),
+            size: Some(body.len() as u64),
+        }
+    }
+
+    fn manager() -> DownloadManager {
+        DownloadManager::new()
+            .unwrap()
+            .with_retries(3, Duration::from_millis(1))
+    }
+
+    fn requests(server: &TestServer, path: &str) -> Vec<crate::api::test_server::Request> {
+        server
+            .requests()
+            .into_iter()
+            .filter(|r| r.path == path)
+            .collect()
+    }
+
+    #[test]
+    fn test_download_all() {
+        let server = TestServer::start();
+        let dir = tempfile::tempdir().unwrap();
+        let bodies: Vec<Vec<u8>> = (0..10_u8).map(|i| vec![i; 1000 * i as usize]).collect();
+        let tasks: Vec<_> = bodies
+            .iter()
+            .enumerate()
+            .map(|(i, body)| task(&server, dir.path(), &format!("a/{}", i), body))
+            .collect();
+        //已经有效的文件直接跳过, 内容不对的重新下载
+        fs::create_dir_all(dir.path().join("a")).unwrap();
+        fs::write(&tasks[3].path, &bodies[3]).unwrap();
+        fs::write(&tasks[4].path, vec![0; 4000]).unwrap();
+
+        let reports = Mutex::new(Vec::new());
+        let progress = manager()
+            .with_concurrency(3)
+            .run(tasks.clone(), &CancelToken::new(), |p| {
+                reports.lock().unwrap().push(p.clone())
+            })
+            .unwrap();
+        for (task, body) in tasks.iter().zip(&bodies) {
+            assert_eq!(&fs::read(&task.path).unwrap(), body);
+            assert!(!part_path(&task.path).exists());
+        }
+        assert!(requests(&server, "/a/3").is_empty());
+        assert_eq!(requests(&server, "/a/4").len(), 1);
+        assert_eq!(
+            progress,
+            DownloadProgress {
+                total_files: 10,
+                finished_files: 10,
+                skipped_files: 1,
+                failed_files: 0,
+                total_bytes: 45000,
+                downloaded_bytes: 45000,
+            }
+        );
+        let reports = reports.into_inner().unwrap();
+        assert_eq!(reports.last(), Some(&progress));
+        assert!(reports
+            .windows(2)
+            .all(|w| w[0].finished_files <= w[1].finished_files));
+
+        //没有任务时直接完成
+        let empty = manager().run(Vec::new(), &CancelToken::new(), |_| {});
+        assert_eq!(empty.unwrap(), DownloadProgress::default());
+    }
+
+    #[test]
+    fn test_duplicate_tasks() {
+        let server = TestServer::start();
+        let dir = tempfile::tempdir().unwrap();
+        let body = vec![7; 200_000];
+        let object = task(&server, dir.path(), "object", &body);
+        let progress = manager()
+            .with_concurrency(4)
+            .run(vec![object.clone(); 2], &CancelToken::new(), |_| {})
+            .unwrap();
+        assert_eq!(fs::read(&object.path).unwrap(), body);
+        assert_eq!(requests(&server, "/object").len(), 1);
+        assert_eq!((progress.total_files, progress.finished_files), (1, 1));
+        assert_eq!(progress.total_bytes, 200_000);
+
+        //同一个路径的 sha1 或大小不一样
+        let mut other = object.clone();
+        other.size = Some(1);
+        let error = manager()
+            .run(vec![object.clone(), other], &CancelToken::new(), |_| {})
+            .unwrap_err();
+        assert!(error.to_string().contains("Conflicting downloads"));
+    }
+
+    #[test]
+    fn test_retry() {
+        let server = TestServer::start();
+        let dir = tempfile::tempdir().unwrap();
+        let flaky = task(&server, dir.path(), "flaky", b"abc");
+        server.inject("/flaky", &[Fault::Status(500), Fault::Status(503)]);
+        manager()
+            .run(vec![flaky.clone()], &CancelToken::new(), |_| {})
+            .unwrap();
+        assert_eq!(fs::read(&flaky.path).unwrap(), b"abc");
+        assert_eq!(requests(&server, "/flaky").len(), 3);
+
+        //重试次数用完
+        let broken = task(&server, dir.path(), "broken", b"abc");
+        server.inject("/broken", &vec![Fault::Status(500); 4]);
+        let error = manager()
+            .run(vec![broken.clone()], &CancelToken::new(), |_| {})
+            .unwrap_err();
+        assert!(error.to_string().contains("500"));
+        assert_eq!(requests(&server, "/broken").len(), 4);
+
+        //404 不重试, 其他文件照常下载
+        let missing = DownloadTask {
+            url: server.url("/missing"),
+            path: dir.path().join("missing"),
+            sha1: None,
+            size: None,
+        };
+        let ok = task(&server, dir.path(), "ok", b"ok");
+        let last = Mutex::new(DownloadProgress::default());
+        let error = manager().run(
+            vec![missing.clone(), ok.clone()],
+            &CancelToken::new(),
+            |p| *last.lock().unwrap() = p.clone(),
+        );
+        let last = last.into_inner().unwrap();
+        assert!(error.unwrap_err().to_string().contains("1 of 2 files"));
+        assert_eq!(requests(&server, "/missing").len(), 1);
+        assert_eq!(fs::read(&ok.path).unwrap(), b"ok");
+        assert!(!missing.path.exists());
+        assert_eq!((last.finished_files, last.failed_files), (1, 1));
+        assert_eq!(last.downloaded_bytes, 2);
+    }
+
+    #[test]
+    fn test_resume() {
+        let server = TestServer::start();
+        let dir = tempfile::tempdir().unwrap();
+        let body: Vec<u8> = (0..100_000_u32).map(|i| (i % 251) as u8).collect();
+
+        //响应体被截断后从断开的位置继续
+        let truncated = task(&server, dir.path(), "truncated", &body);
+        server.inject("/truncated", &[Fault::Truncate(40_000)]);
+        manager()
+            .run(vec![truncated.clone()], &CancelToken::new(), |_| {})
+            .unwrap();
+        assert_eq!(fs::read(&truncated.path).unwrap(), body);
+        let sent = requests(&server, "/truncated");
+        assert_eq!(sent.len(), 2);
+        assert!(!sent[0].headers.contains_key("range"));
+        assert_eq!(sent[1].headers["range"], "bytes=40000-");
+
+        //上次留下的 .part 文件
+        let partial = task(&server, dir.path(), "partial", &body);
+        fs::write(part_path(&partial.path), &body[..70_000]).unwrap();
+        manager()
+            .run(vec![partial.clone()], &CancelToken::new(), |_| {})
+            .unwrap();
+        assert_eq!(fs::read(&partial.path).unwrap(), body);
+        assert_eq!(
+            requests(&server, "/partial")[0].headers["range"],
+            "bytes=70000-"
+        );
+
+        //.part 内容不对时校验失败, 删除后从头下载
+        let corrupt = task(&server, dir.path(), "corrupt", &body);
+        fs::write(part_path(&corrupt.path), vec![0; 50_000]).unwrap();
+        manager()
+            .run(vec![corrupt.clone()], &CancelToken::new(), |_| {})
+            .unwrap();
+        assert_eq!(fs::read(&corrupt.path).unwrap(), body);
+        let sent = requests(&server, "/corrupt");
+        assert_eq!(sent.len(), 2);
+        assert!(!sent[1].headers.contains_key("range"));
+
+        //服务器上的文件和 sha1 对不上
+        let mut wrong = task(&server, dir.path(), "wrong", &body);
+        wrong.sha1 = Some("0".repeat(40));
+        let error = manager()
+            .run(vec![wrong.clone()], &CancelToken::new(), |_| {})
+            .unwrap_err();
+        assert!(error.to_string().contains("Checksum mismatch"));
+        assert!(!wrong.path.exists());
+        assert!(!part_path(&wrong.path).exists());
+    }
+
+    #[test]
+    fn test_cancel() {
+        let server = TestServer::start();
+        let dir = tempfile::tempdir().unwrap();
+        let tasks: Vec<_> = (0..5)
+            .map(|i| task(&server, dir.path(), &i.to_string(), b"abc"))
+            .collect();
+
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        assert!(manager().run(tasks.clone(), &cancel, |_| {}).is_err());
+        assert!(server.requests().is_empty());
+
+        let cancel = CancelToken::new();
+        let error = manager()
+            .with_concurrency(1)
+            .run(tasks.clone(), &cancel, |p| {
+                if p.finished_files == 2 {
+                    cancel.cancel();
+                }
+            })
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Download cancelled");
+        let downloaded: Vec<_> = tasks.iter().map(|t| t.path.exists()).collect();
+        assert_eq!(downloaded, [true, true, false, false, false]);
+    }
+}
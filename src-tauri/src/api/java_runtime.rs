@@ -0,0 +1,716 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    download::{self, Checksum},
+    version::Version,
+};
+
+pub const MOJANG_RUNTIME_MANIFEST: &str = "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+//java 运行时的元数据来源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RuntimeSource {
+    Mojang { manifest_url: String },
+    Adoptium { base_url: String },
+}
+
+impl Default for RuntimeSource {
+    fn default() -> Self {
+        RuntimeSource::Mojang {
+            manifest_url: MOJANG_RUNTIME_MANIFEST.to_string(),
+        }
+    }
+}
+
+pub struct RuntimeInstaller {
+    source: RuntimeSource,
+    runtime_dir: PathBuf,
+    client: Client,
+}
+
+impl RuntimeInstaller {
+    pub fn new(source: RuntimeSource, runtime_dir: PathBuf) -> Result<Self> {
+        Ok(RuntimeInstaller {
+            source,
+            runtime_dir,
+            client: download::client()?,
+        })
+    }
+
+    //安装指定大版本的 java, 返回 java 可执行文件的路径
+    pub fn install(&self, major: u32) -> Result<PathBuf> {
+        fs::create_dir_all(&self.runtime_dir)?;
+        let home = match &self.source {
+            RuntimeSource::Mojang { manifest_url } => self.install_mojang(manifest_url, major)?,
+            RuntimeSource::Adoptium { base_url } => self.install_adoptium(base_url, major)?,
+        };
+        find_java(&home).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No java executable found in installed runtime `{}`",
+                home.display()
+            )
+        })
+    }
+
+    fn install_mojang(&self, manifest_url: &str, major: u32) -> Result<PathBuf> {
+        let all: HashMap<String, HashMap<String, Vec<MojangRuntime>>> =
+            download::get_json(&self.client, manifest_url)?;
+        let platform = mojang_platform()?;
+        let components = all
+            .get(platform)
+            .ok_or_else(|| anyhow::anyhow!("No java runtime available for `{}`", platform))?;
+        //同一大版本可能有多个组件, 如 java-runtime-gamma 和 java-runtime-gamma-snapshot
+        //按名字排序保证结果固定, 优先不是快照的
+        let (component, runtime) = components
+            .iter()
+            .filter_map(|(name, runtimes)| runtimes.first().map(|r| (name, r)))
+            .filter(|(_, runtime)| runtime_major(&runtime.version.name) == Some(major))
+            .min_by_key(|(name, _)| (name.contains("snapshot"), name.as_str()))
+            .ok_or_else(|| {
+                anyhow::anyhow!("No Java {} runtime available for `{}`", major, platform)
+            })?;
+
+        let manifest_bytes = download::get_bytes(&self.client, &runtime.manifest.url)?;
+        if manifest_bytes.len() as u64 != runtime.manifest.size {
+            bail!(
+                "Size mismatch for runtime manifest `{}`",
+                runtime.manifest.url
+            );
+        }
+        Checksum::Sha1(runtime.manifest.sha1.clone()).verify(&manifest_bytes)?;
+        let manifest: MojangRuntimeManifest = serde_json::from_slice(&manifest_bytes)?;
+
+        let home = self.runtime_dir.join(component);
+        let staging = self.runtime_dir.join(format!(".{}.tmp", component));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        fs::create_dir_all(&staging)?;
+        if let Err(e) = self.download_mojang_files(&manifest, &staging) {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        replace_dir(&staging, &home)?;
+        Ok(home)
+    }
+
+    fn download_mojang_files(&self, manifest: &MojangRuntimeManifest, dir: &Path) -> Result<()> {
+        let mut links = Vec::new();
+        for (name, file) in &manifest.files {
+            let path = safe_join(dir, Path::new(name))?;
+            match file {
+                MojangRuntimeFile::Directory => fs::create_dir_all(&path)?,
+                MojangRuntimeFile::File {
+                    downloads,
+                    executable,
+                } => {
+                    let raw = &downloads.raw;
+                    download::download_file(
+                        &self.client,
+                        &raw.url,
+                        &path,
+                        Some(&Checksum::Sha1(raw.sha1.clone())),
+                        Some(raw.size),
+                    )?;
+                    if *executable {
+                        set_executable(&path)?;
+                    }
+                }
+                MojangRuntimeFile::Link { target } => {
+                    let resolved = resolve_link(dir, Path::new(name), Path::new(target))?;
+                    links.push((path, target.clone(), resolved));
+                }
+            }
+        }
+        //链接指向的文件可能还没下载, 最后再创建
+        for (path, target, resolved) in links {
+            create_link(&path, Path::new(&target), &resolved)?;
+        }
+        Ok(())
+    }
+
+    fn install_adoptium(&self, base_url: &str, major: u32) -> Result<PathBuf> {
+        let (os, arch) = adoptium_platform()?;
+        let url = format!(
+            "{}/v3/assets/latest/{}/hotspot?architecture={}&image_type=jre&os={}&vendor=eclipse",
+            base_url.trim_end_matches('/'),
+            major,
+            arch,
+            os
+        );
+        let assets: Vec<AdoptiumAsset> = download::get_json(&self.client, &url)?;
+        let asset = assets.into_iter().next().ok_or_else(|| {
+            anyhow::anyhow!("No Java {} runtime available for {} {}", major, os, arch)
+        })?;
+
+        let package = &asset.binary.package;
+        let archive = self.runtime_dir.join(safe_file_name(&package.name)?);
+        download::download_file(
+            &self.client,
+            &package.link,
+            &archive,
+            Some(&Checksum::Sha256(package.checksum.clone())),
+            Some(package.size),
+        )?;
+
+        let name = safe_file_name(&asset.release_name)?;
+        let staging = self.runtime_dir.join(format!(".{}.tmp", name));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        let extracted = extract(&archive, &staging);
+        let _ = fs::remove_file(&archive);
+        extracted?;
+
+        //压缩包里通常只有一个顶层目录, 就是 java home, 否则整个解压目录就是 java home
+        let home = self.runtime_dir.join(name);
+        let extracted_home = single_child_dir(&staging)?;
+        replace_dir(&extracted_home, &home)?;
+        if extracted_home != staging {
+            fs::remove_dir_all(&staging)?;
+        }
+        Ok(home)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MojangRuntime {
+    manifest: MojangDownload,
+    version: MojangRuntimeVersion,
+}
+
+#[derive(Debug, Deserialize)]
+struct MojangRuntimeVersion {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MojangDownload {
+    sha1: String,
+    size: u64,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MojangRuntimeManifest {
+    files: HashMap<String, MojangRuntimeFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum MojangRuntimeFile {
+    Directory,
+    File {
+        downloads: MojangFileDownloads,
+        #[serde(default)]
+        executable: bool,
+    },
+    Link {
+        target: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct MojangFileDownloads {
+    raw: MojangDownload,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdoptiumAsset {
+    binary: AdoptiumBinary,
+    release_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdoptiumBinary {
+    package: AdoptiumPackage,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdoptiumPackage {
+    checksum: String,
+    link: String,
+    name: String,
+    size: u64,
+}
+
+//mojang 的版本名形如 17.0.8 或 8u51
+fn runtime_major(name: &str) -> Option<u32> {
+    Version::from_java_string(name).ok().map(|v| v.major())
+}
+
+fn mojang_platform() -> Result<&'static str> {
+    let platform = match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => "linux",
+        ("linux", "x86") => "linux-i386",
+        ("macos", "x86_64") => "mac-os",
+        ("macos", "aarch64") => "mac-os-arm64",
+        ("windows", "x86_64") => "windows-x64",
+        ("windows", "x86") => "windows-x86",
+        ("windows", "aarch64") => "windows-arm64",
+        (os, arch) => bail!("Unsupported platform {} {}", os, arch),
+    };
+    Ok(platform)
+}
+
+fn adoptium_platform() -> Result<(&'static str, &'static str)> {
+    let os = match std::env::consts::OS {
+        "linux" => "linux",
+        "macos" => "mac",
+        "windows" => "windows",
+        os => bail!("Unsupported os {}", os),
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "x86" => "x86",
+        "aarch64" => "aarch64",
+        "arm" => "arm",
+        arch => bail!("Unsupported arch {}", arch),
+    };
+    Ok((os, arch))
+}
+
+fn extract(archive: &Path, dest: &Path) -> Result<()> {
+    fs::create_dir_all(dest)?;
+    let name = archive.to_string_lossy();
+    if name.ends_with(".zip") {
+        let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i)?;
+            let Some(relative) = entry.enclosed_name() else {
+                bail!("Unsafe path `{}` in `{}`", entry.name(), name);
+            };
+            let path = dest.join(relative);
+            if entry.is_dir() {
+                fs::create_dir_all(&path)?;
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut entry, &mut File::create(&path)?)?;
+            #[cfg(unix)]
+            if let Some(mode) = entry.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+            }
+        }
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        let gz = flate2::read::GzDecoder::new(File::open(archive)?);
+        let mut tar = tar::Archive::new(gz);
+        tar.set_preserve_permissions(true);
+        tar.unpack(dest)?;
+    } else {
+        bail!("Unsupported archive `{}`", name);
+    }
+    Ok(())
+}
+
+fn single_child_dir(dir: &Path) -> Result<PathBuf> {
+    let children: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    match children.as_slice() {
+        [child] if child.is_dir() => Ok(child.clone()),
+        _ => Ok(dir.to_path_buf()),
+    }
+}
+
+fn replace_dir(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        fs::remove_dir_all(to)?;
+    }
+    fs::rename(from, to)?;
+    Ok(())
+}
+
+//防止清单里的路径跳出安装目录
+fn safe_join(base: &Path, relative: &Path) -> Result<PathBuf> {
+    if relative.is_absolute()
+        || relative
+            .components()
+            .any(|c| !matches!(c, std::path::Component::Normal(_)))
+    {
+        bail!("Unsafe path `{}` in runtime manifest", relative.display());
+    }
+    Ok(base.join(relative))
+}
+
+//链接的目标相对于链接所在的目录, 不能指向运行时目录之外
+fn resolve_link(base: &Path, link: &Path, target: &Path) -> Result<PathBuf> {
+    let mut resolved = PathBuf::new();
+    let parent = link.parent().unwrap_or(Path::new(""));
+    for component in parent.components().chain(target.components()) {
+        match component {
+            std::path::Component::Normal(part) => resolved.push(part),
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir if resolved.pop() => {}
+            _ => bail!(
+                "Unsafe link target `{}` for `{}` in runtime manifest",
+                target.display(),
+                link.display()
+            ),
+        }
+    }
+    safe_join(base, &resolved)
+}
+
+fn safe_file_name(name: &str) -> Result<&str> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        bail!("Unsafe file name `{}`", name);
+    }
+    Ok(name)
+}
+
+fn find_java(home: &Path) -> Option<PathBuf> {
+    let exe = if cfg!(windows) { "java.exe" } else { "java" };
+    [
+        home.to_path_buf(),
+        home.join("Contents").join("Home"),
+        home.join("jre.bundle").join("Contents").join("Home"),
+    ]
+    .into_iter()
+    .map(|h| h.join("bin").join(exe))
+    .find(|p| p.is_file())
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_link(path: &Path, target: &Path, _resolved: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    std::os::unix::fs::symlink(target, path)?;
+    Ok(())
+}
+
+//windows 上创建符号链接需要权限, 直接复制目标文件
+#[cfg(not(unix))]
+fn create_link(path: &Path, _target: &Path, resolved: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(resolved, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use sha1::Sha1;
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::api::test_server::TestServer;
+
+    const FAKE_JAVA: &[u8] = b"#!/bin/sh\necho fake java\n";
+
+    fn sha1_hex(data: &[u8]) -> String {
+        hex::encode(Sha1::digest(data))
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+
+    fn fixture_tar_gz() -> Vec<u8> {
+        tar_gz_with_prefix("jdk-17.0.10+7-jre/")
+    }
+
+    //prefix 为空时压缩包没有顶层目录
+    fn tar_gz_with_prefix(prefix: &str) -> Vec<u8> {
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        for (path, data, mode) in [
+            ("bin/java", FAKE_JAVA, 0o755),
+            ("release", &b"JAVA_VERSION=\"17.0.10\"\n"[..], 0o644),
+        ] {
+            let path = format!("{}{}", prefix, path);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(mode);
+            header.set_cksum();
+            tar.append_data(&mut header, path, data).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn fixture_zip() -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default().unix_permissions(0o755);
+        zip.start_file("jdk-17.0.10+7-jre/bin/java.exe", options)
+            .unwrap();
+        zip.write_all(FAKE_JAVA).unwrap();
+        zip.start_file("jdk-17.0.10+7-jre/bin/java", options)
+            .unwrap();
+        zip.write_all(FAKE_JAVA).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn serve_adoptium(server: &TestServer, archive: Vec<u8>, name: &str, checksum: String) {
+        let (os, arch) = adoptium_platform().unwrap();
+        let size = archive.len();
+        server.set(&format!("/archive/{}", name), archive);
+        let assets = serde_json::json!([{
+            "binary": {
+                "architecture": arch,
+                "image_type": "jre",
+                "os": os,
+                "package": {
+                    "checksum": checksum,
+                    "link": server.url(&format!("/archive/{}", name)),
+                    "name": name,
+                    "size": size
+                }
+            },
+            "release_name": "jdk-17.0.10+7",
+            "version": { "major": 17, "minor": 0, "security": 10, "semver": "17.0.10+7" }
+        }]);
+        server.set(
+            &format!(
+                "/v3/assets/latest/17/hotspot?architecture={}&image_type=jre&os={}&vendor=eclipse",
+                arch, os
+            ),
+            assets.to_string().into_bytes(),
+        );
+    }
+
+    #[test]
+    fn test_install_adoptium_tar_gz() {
+        let server = TestServer::start();
+        let archive = fixture_tar_gz();
+        let checksum = sha256_hex(&archive);
+        serve_adoptium(&server, archive, "jre.tar.gz", checksum);
+
+        let runtime_dir = tempfile::tempdir().unwrap();
+        let installer = RuntimeInstaller::new(
+            RuntimeSource::Adoptium {
+                base_url: server.url(""),
+            },
+            runtime_dir.path().to_path_buf(),
+        )
+        .unwrap();
+        let java = installer.install(17).unwrap();
+        assert!(java.starts_with(runtime_dir.path().join("jdk-17.0.10+7")));
+        assert_eq!(fs::read(&java).unwrap(), FAKE_JAVA);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&java).unwrap().permissions().mode();
+            assert_eq!(mode & 0o111, 0o111);
+        }
+        assert!(!runtime_dir.path().join("jre.tar.gz").exists());
+        assert!(!runtime_dir.path().join(".jdk-17.0.10+7.tmp").exists());
+
+        //重复安装会覆盖旧的
+        assert_eq!(installer.install(17).unwrap(), java);
+        assert!(installer.install(8).is_err());
+    }
+
+    #[test]
+    fn test_install_adoptium_flat_archive() {
+        let server = TestServer::start();
+        let archive = tar_gz_with_prefix("");
+        let checksum = sha256_hex(&archive);
+        serve_adoptium(&server, archive, "jre.tar.gz", checksum);
+
+        let runtime_dir = tempfile::tempdir().unwrap();
+        let installer = RuntimeInstaller::new(
+            RuntimeSource::Adoptium {
+                base_url: server.url(""),
+            },
+            runtime_dir.path().to_path_buf(),
+        )
+        .unwrap();
+        let java = installer.install(17).unwrap();
+        assert!(java.starts_with(runtime_dir.path().join("jdk-17.0.10+7")));
+        assert_eq!(fs::read(&java).unwrap(), FAKE_JAVA);
+        assert!(runtime_dir
+            .path()
+            .join("jdk-17.0.10+7")
+            .join("release")
+            .exists());
+        assert!(!runtime_dir.path().join(".jdk-17.0.10+7.tmp").exists());
+    }
+
+    #[test]
+    fn test_install_adoptium_zip() {
+        let server = TestServer::start();
+        let archive = fixture_zip();
+        let checksum = sha256_hex(&archive);
+        serve_adoptium(&server, archive, "jre.zip", checksum);
+
+        let runtime_dir = tempfile::tempdir().unwrap();
+        let installer = RuntimeInstaller::new(
+            RuntimeSource::Adoptium {
+                base_url: server.url("/"),
+            },
+            runtime_dir.path().to_path_buf(),
+        )
+        .unwrap();
+        let java = installer.install(17).unwrap();
+        assert_eq!(fs::read(java).unwrap(), FAKE_JAVA);
+    }
+
+    #[test]
+    fn test_install_adoptium_bad_checksum() {
+        let server = TestServer::start();
+        serve_adoptium(
+            &server,
+            fixture_tar_gz(),
+            "jre.tar.gz",
+            sha256_hex(b"other"),
+        );
+
+        let runtime_dir = tempfile::tempdir().unwrap();
+        let installer = RuntimeInstaller::new(
+            RuntimeSource::Adoptium {
+                base_url: server.url(""),
+            },
+            runtime_dir.path().to_path_buf(),
+        )
+        .unwrap();
+        assert!(installer.install(17).is_err());
+        assert!(!runtime_dir.path().join("jdk-17.0.10+7").exists());
+    }
+
+    fn serve_mojang(server: &TestServer, java_sha1: String) {
+        let manifest = serde_json::json!({
+            "files": {
+                "bin": { "type": "directory" },
+                "bin/java": {
+                    "type": "file",
+                    "executable": true,
+                    "downloads": {
+                        "raw": { "sha1": java_sha1, "size": FAKE_JAVA.len(), "url": server.url("/files/java") }
+                    }
+                },
+                "bin/java.exe": {
+                    "type": "file",
+                    "downloads": {
+                        "raw": { "sha1": sha1_hex(FAKE_JAVA), "size": FAKE_JAVA.len(), "url": server.url("/files/java") }
+                    }
+                },
+                "legal/LICENSE": { "type": "link", "target": "../bin/java" }
+            }
+        })
+        .to_string()
+        .into_bytes();
+        let all = serde_json::json!({
+            mojang_platform().unwrap(): {
+                "java-runtime-gamma": [{
+                    "availability": { "group": 1, "progress": 100 },
+                    "manifest": { "sha1": sha1_hex(&manifest), "size": manifest.len(), "url": server.url("/manifest.json") },
+                    "version": { "name": "17.0.8", "released": "2023-07-19T12:00:00+00:00" }
+                }],
+                //同样是 17, 不应该被选中
+                "java-runtime-gamma-snapshot": [{
+                    "availability": { "group": 1, "progress": 100 },
+                    "manifest": { "sha1": "0", "size": 2, "url": server.url("/snapshot.json") },
+                    "version": { "name": "17.0.9", "released": "2023-10-17T12:00:00+00:00" }
+                }],
+                "jre-legacy": [{
+                    "availability": { "group": 1, "progress": 100 },
+                    "manifest": { "sha1": "0", "size": 2, "url": server.url("/legacy.json") },
+                    "version": { "name": "8u51", "released": "2015-07-15T12:00:00+00:00" }
+                }],
+                "java-runtime-delta": []
+            }
+        });
+        server.set("/files/java", FAKE_JAVA.to_vec());
+        server.set("/legacy.json", b"{}".to_vec());
+        server.set("/manifest.json", manifest);
+        server.set("/all.json", all.to_string().into_bytes());
+    }
+
+    #[test]
+    fn test_install_mojang() {
+        let server = TestServer::start();
+        serve_mojang(&server, sha1_hex(FAKE_JAVA));
+
+        let runtime_dir = tempfile::tempdir().unwrap();
+        let installer = RuntimeInstaller::new(
+            RuntimeSource::Mojang {
+                manifest_url: server.url("/all.json"),
+            },
+            runtime_dir.path().to_path_buf(),
+        )
+        .unwrap();
+        let java = installer.install(17).unwrap();
+        assert!(java.starts_with(runtime_dir.path().join("java-runtime-gamma/")));
+        assert_eq!(fs::read(&java).unwrap(), FAKE_JAVA);
+        #[cfg(unix)]
+        assert_eq!(
+            fs::read(runtime_dir.path().join("java-runtime-gamma/legal/LICENSE")).unwrap(),
+            FAKE_JAVA
+        );
+        //清单的 sha1 不对
+        assert!(installer.install(8).is_err());
+        assert!(installer.install(21).is_err());
+    }
+
+    #[test]
+    fn test_install_mojang_bad_checksum() {
+        let server = TestServer::start();
+        serve_mojang(&server, sha1_hex(b"other"));
+
+        let runtime_dir = tempfile::tempdir().unwrap();
+        let installer = RuntimeInstaller::new(
+            RuntimeSource::Mojang {
+                manifest_url: server.url("/all.json"),
+            },
+            runtime_dir.path().to_path_buf(),
+        )
+        .unwrap();
+        assert!(installer.install(17).is_err());
+        assert!(!runtime_dir.path().join("java-runtime-gamma").exists());
+    }
+
+    #[test]
+    fn test_safe_paths() {
+        let base = Path::new("/runtime");
+        assert!(safe_join(base, Path::new("bin/java")).is_ok());
+        assert!(safe_join(base, Path::new("../bin/java")).is_err());
+        assert!(safe_join(base, Path::new("/bin/java")).is_err());
+        assert_eq!(
+            resolve_link(base, Path::new("legal/LICENSE"), Path::new("../bin/java")).unwrap(),
+            base.join("bin/java")
+        );
+        assert_eq!(
+            resolve_link(base, Path::new("lib/a"), Path::new("./b")).unwrap(),
+            base.join("lib/b")
+        );
+        assert!(resolve_link(base, Path::new("legal/LICENSE"), Path::new("../../etc")).is_err());
+        assert!(resolve_link(base, Path::new("LICENSE"), Path::new("/etc/passwd")).is_err());
+        assert!(safe_file_name("jdk-17.0.10+7").is_ok());
+        assert!(safe_file_name("../jdk").is_err());
+        assert_eq!(runtime_major("8u51"), Some(8));
+        assert_eq!(runtime_major("17.0.8"), Some(17));
+        assert_eq!(runtime_major("1.8.0_51"), Some(8));
+    }
+}
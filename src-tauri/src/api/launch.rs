@@ -0,0 +1,234 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::settings::java_versions::{
+    required_java_major, JavaSelection, JavaVersions, LOWEST_JAVA_MAJOR,
+};
+
+use super::{classpath::Classpath, rules::Environment, version_json::VersionJson};
+
+//启动一个版本需要的全部信息, 账号相关的 ${auth_*} 等变量留给调用者替换
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchPlan {
+    pub java: JavaSelection,
+    pub main_class: String,
+    pub classpath: Classpath,
+    //每次启动单独的目录, 游戏退出后可以删除
+    pub natives_dir: PathBuf,
+    pub jvm_arguments: Vec<String>,
+    pub game_arguments: Vec<String>,
+}
+
+//游戏目录中的 versions 和 libraries
+pub struct GameDirs {
+    pub game_dir: PathBuf,
+    pub versions_dir: PathBuf,
+    pub libraries_dir: PathBuf,
+    pub assets_dir: PathBuf,
+}
+
+impl GameDirs {
+    pub fn new(game_dir: &Path) -> Self {
+        GameDirs {
+            game_dir: game_dir.to_path_buf(),
+            versions_dir: game_dir.join("versions"),
+            libraries_dir: game_dir.join("libraries"),
+            assets_dir: game_dir.join("assets"),
+        }
+    }
+
+    pub fn client_jar(&self, version: &VersionJson) -> PathBuf {
+        let id = version.jar_id();
+        self.versions_dir.join(id).join(format!("{}.jar", id))
+    }
+}
+
+//版本需要的 java, 版本 json 没有 javaVersion 时按游戏版本推断
+//jar 不是标准的游戏版本时按最低的要求, 选中的 java 满足要求时使用选中的
+pub fn select_java(java_versions: &JavaVersions, version: &VersionJson) -> Result<JavaSelection> {
+    let required = match &version.java_version {
+        Some(java) => java.major_version,
+        None => required_java_major(version.jar_id(), None).unwrap_or(LOWEST_JAVA_MAJOR),
+    };
+    java_versions.resolve_selected(required)
+}
+
+impl LaunchPlan {
+    //读取并合并 game_dir 中的版本 json, 按当前系统和选中的 java 准备启动
+    pub fn for_version(
+        game_dir: &Path,
+        version_id: &str,
+        java_versions: &JavaVersions,
+    ) -> Result<Self> {
+        let dirs = GameDirs::new(game_dir);
+        let version = VersionJson::resolve(&dirs.versions_dir, version_id)?;
+        let env = Environment::current().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unsupported system {} {}",
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            )
+        })?;
+        let java = select_java(java_versions, &version)?;
+        Self::prepare(&version, &env, &dirs, java)
+    }
+
+    //解析 classpath 并解压本地库, 替换和启动器、路径相关的变量
+    pub fn prepare(
+        version: &VersionJson,
+        env: &Environment,
+        dirs: &GameDirs,
+        java: JavaSelection,
+    ) -> Result<Self> {
+        let main_class = version
+            .main_class
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Version `{}` has no main class", version.id))?;
+        let classpath =
+            Classpath::build(version, env, &dirs.libraries_dir, &dirs.client_jar(version))?;
+        let natives_dir = dirs
+            .versions_dir
+            .join(&version.id)
+            .join(format!("natives-{}", Utc::now().format("%Y%m%d%H%M%S%3f")));
+        classpath.extract_natives(&natives_dir)?;
+
+        let path = |p: &Path| p.to_string_lossy().to_string();
+        let variables: HashMap<&str, String> = HashMap::from([
+            ("natives_directory", path(&natives_dir)),
+            ("launcher_name", "rev-launcher".to_string()),
+            ("launcher_version", env!("CARGO_PKG_VERSION").to_string()),
+            ("classpath", classpath.to_string()),
+            ("classpath_separator", classpath.separator().to_string()),
+            ("library_directory", path(&dirs.libraries_dir)),
+            ("version_name", version.id.clone()),
+            ("game_directory", path(&dirs.game_dir)),
+            ("assets_root", path(&dirs.assets_dir)),
+            (
+                "assets_index_name",
+                version.assets.clone().unwrap_or_default(),
+            ),
+            (
+                "version_type",
+                version
+                    .version_type
+                    .map(|t| t.as_str().to_string())
+                    .unwrap_or_default(),
+            ),
+        ]);
+        let substitute_all = |arguments: Vec<String>| -> Vec<String> {
+            arguments
+                .iter()
+                .map(|argument| substitute(argument, &variables))
+                .collect()
+        };
+        Ok(LaunchPlan {
+            java,
+            main_class,
+            jvm_arguments: substitute_all(env.jvm_arguments(version)),
+            game_arguments: substitute_all(env.game_arguments(version)),
+            classpath,
+            natives_dir,
+        })
+    }
+}
+
+//替换 ${name}, 不认识的变量保持原样
+pub fn substitute(argument: &str, variables: &HashMap<&str, String>) -> String {
+    let mut result = String::new();
+    let mut rest = argument;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + end];
+        match variables.get(name) {
+            Some(value) => result.push_str(value),
+            None => result.push_str(&rest[start..start + end + 1]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::rules::{Arch, OsName};
+
+    #[test]
+    fn test_substitute() {
+        let variables = HashMap::from([("a", "1".to_string()), ("b", "${a}".to_string())]);
+        assert_eq!(substitute("-Dx=${a}", &variables), "-Dx=1");
+        assert_eq!(substitute("${a}${b}${c}", &variables), "1${a}${c}");
+        assert_eq!(substitute("${a", &variables), "${a");
+    }
+
+    #[test]
+    fn test_prepare() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/versions");
+        let version = VersionJson::resolve(&fixtures, "fabric-loader-0.15.11-1.20.1").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let dirs = GameDirs::new(dir.path());
+
+        let java_versions = |select: u8| -> JavaVersions {
+            serde_json::from_value(serde_json::json!({
+                "versions": [
+                    { "path": "/java/8/bin/java", "version": "1.8.0_381" },
+                    { "path": "/java/17/bin/java", "version": "17.0.10" },
+                    { "path": "/java/21/bin/java", "version": "21.0.2" },
+                ],
+                "select": select,
+            }))
+            .unwrap()
+        };
+        //选中的 java 太旧, 挑选最合适的
+        let java = select_java(&java_versions(0), &version).unwrap();
+        assert_eq!(java.path, "/java/17/bin/java");
+        //选中的 java 满足要求
+        let java = select_java(&java_versions(2), &version).unwrap();
+        assert_eq!((java.index, java.path.as_str()), (2, "/java/21/bin/java"));
+        let java = select_java(&java_versions(1), &version).unwrap();
+        assert_eq!(java.path, "/java/17/bin/java");
+        //没有 javaVersion 且 jar 名无法识别时也能启动
+        let custom = VersionJson {
+            id: "custom-pack".to_string(),
+            ..Default::default()
+        };
+        let custom_java = select_java(&java_versions(0), &custom).unwrap();
+        assert_eq!(custom_java.path, "/java/8/bin/java");
+
+        let linux = Environment::new(OsName::Linux, Arch::X86_64, "6.1.0");
+        let plan = LaunchPlan::prepare(&version, &linux, &dirs, java).unwrap();
+        assert_eq!(
+            plan.main_class,
+            "net.fabricmc.loader.impl.launch.knot.KnotClient"
+        );
+        assert!(plan.natives_dir.is_dir());
+        assert!(plan
+            .natives_dir
+            .starts_with(dirs.versions_dir.join(&version.id)));
+        let natives = format!("-Djava.library.path={}", plan.natives_dir.display());
+        assert_eq!(plan.jvm_arguments[0], natives);
+        let cp = plan.jvm_arguments.iter().position(|a| a == "-cp").unwrap();
+        assert_eq!(plan.jvm_arguments[cp + 1], plan.classpath.to_string());
+        assert!(plan.jvm_arguments[cp + 1].ends_with("versions/1.20.1/1.20.1.jar"));
+
+        let game = &plan.game_arguments;
+        let value = |name: &str| {
+            let index = game.iter().position(|a| a == name).unwrap();
+            game[index + 1].as_str()
+        };
+        assert_eq!(value("--version"), "fabric-loader-0.15.11-1.20.1");
+        assert_eq!(value("--assetIndex"), "5");
+        assert_eq!(value("--versionType"), "release");
+        assert_eq!(value("--username"), "${auth_player_name}");
+    }
+}
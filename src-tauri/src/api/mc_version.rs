@@ -0,0 +1,513 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use chrono::{DateTime, Utc};
+
+use super::version::Version;
+
+//版本 id => 发布时间, 来自版本清单
+pub type ReleaseTimes = HashMap<String, DateTime<Utc>>;
+
+//快照开始的年份和周数 => 对应的正式版, 按时间排序
+//一个快照属于它之前最近的一项
+const SNAPSHOT_SERIES: &[(u32, u32, &str)] = &[
+    (11, 47, "1.1"),
+    (12, 1, "1.2.1"),
+    (12, 15, "1.3.1"),
+    (12, 32, "1.4.2"),
+    (12, 49, "1.4.6"),
+    (13, 1, "1.5"),
+    (13, 16, "1.6"),
+    (13, 36, "1.7"),
+    (13, 47, "1.7.4"),
+    (14, 2, "1.8"),
+    (15, 31, "1.9"),
+    (16, 14, "1.9.3"),
+    (16, 20, "1.10"),
+    (16, 32, "1.11"),
+    (16, 50, "1.11.1"),
+    (17, 6, "1.12"),
+    (17, 31, "1.12.1"),
+    (17, 43, "1.13"),
+    (18, 30, "1.13.1"),
+    (18, 43, "1.14"),
+    (19, 34, "1.15"),
+    (20, 6, "1.16"),
+    (20, 27, "1.16.2"),
+    (20, 45, "1.17"),
+    (21, 37, "1.18"),
+    (22, 3, "1.18.2"),
+    (22, 11, "1.19"),
+    (22, 24, "1.19.1"),
+    (22, 42, "1.19.3"),
+    (23, 3, "1.19.4"),
+    (23, 12, "1.20"),
+    (23, 31, "1.20.2"),
+    (23, 40, "1.20.3"),
+    (23, 51, "1.20.5"),
+    (24, 18, "1.21"),
+    (24, 33, "1.21.2"),
+    (24, 44, "1.21.4"),
+    (25, 2, "1.21.5"),
+    (25, 15, "1.21.6"),
+    (25, 31, "1.21.9"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum McVersionKind {
+    Release(Version),
+    PreRelease(Version, u32),
+    ReleaseCandidate(Version, u32),
+    //24w14a, 愚人节的 24w14potato 也算
+    Snapshot {
+        year: u32,
+        week: u32,
+        suffix: String,
+    },
+    Beta(Version),
+    Alpha(Version),
+    Infdev(Version),
+    Indev(Version),
+    Classic(Version),
+    PreClassic(u64),
+    //1.RV-Pokemon 之类无法归类的版本
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct McVersion {
+    id: String,
+    kind: McVersionKind,
+}
+
+impl McVersion {
+    pub fn parse(id: &str) -> Self {
+        let id = id.trim();
+        McVersion {
+            id: id.to_string(),
+            kind: parse_kind(id).unwrap_or(McVersionKind::Other),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn kind(&self) -> &McVersionKind {
+        &self.kind
+    }
+
+    //快照, 预发布版本和候选版本对应的正式版
+    pub fn release_target(&self) -> Option<Version> {
+        match &self.kind {
+            McVersionKind::Release(v)
+            | McVersionKind::PreRelease(v, _)
+            | McVersionKind::ReleaseCandidate(v, _) => Some(v.clone()),
+            McVersionKind::Snapshot { year, week, .. } => snapshot_target(*year, *week),
+            _ => None,
+        }
+    }
+
+    //是否属于某个正式版系列, 如 24w14a 属于 1.20.5, 也属于 1.20
+    pub fn belongs_to(&self, release: &McVersion) -> bool {
+        let (Some(target), McVersionKind::Release(line)) = (self.release_target(), &release.kind)
+        else {
+            return false;
+        };
+        if line.patch().is_some() {
+            return &target == line;
+        }
+        target.major() == line.major() && target.minor().unwrap_or(0) == line.minor().unwrap_or(0)
+    }
+
+    //按发布时间排序, 不在表里的版本使用按版本号排在它前面最近的已知版本的时间,
+    //前面没有已知版本时使用后面最近的, 这样排序的 key 是全序的
+    pub fn sort_chronological(versions: &mut [McVersion], times: Option<&ReleaseTimes>) {
+        versions.sort();
+        let Some(times) = times else {
+            return;
+        };
+        let known: Vec<_> = versions.iter().map(|v| times.get(&v.id).copied()).collect();
+        let first = known.iter().flatten().next().copied();
+        let mut previous = None;
+        let keys: Vec<_> = known
+            .iter()
+            .map(|time| {
+                previous = time.or(previous);
+                previous.or(first)
+            })
+            .collect();
+        let mut keyed: Vec<_> = keys.into_iter().zip(versions.iter().cloned()).collect();
+        //sort 是稳定的, key 相同时保持版本号的顺序
+        keyed.sort_by_key(|(key, _)| *key);
+        for (version, (_, sorted)) in versions.iter_mut().zip(keyed) {
+            *version = sorted;
+        }
+    }
+
+    //时代, 越早越小
+    fn era(&self) -> u8 {
+        match self.kind {
+            McVersionKind::PreClassic(_) => 0,
+            McVersionKind::Classic(_) => 1,
+            McVersionKind::Indev(_) => 2,
+            McVersionKind::Infdev(_) => 3,
+            McVersionKind::Alpha(_) => 4,
+            McVersionKind::Beta(_) => 5,
+            McVersionKind::Release(_)
+            | McVersionKind::PreRelease(..)
+            | McVersionKind::ReleaseCandidate(..)
+            | McVersionKind::Snapshot { .. } => 6,
+            McVersionKind::Other => 7,
+        }
+    }
+
+    //同一个正式版内: 快照 < 预发布 < 候选 < 正式版
+    fn stage(&self) -> (u8, u32, u32, &str) {
+        match &self.kind {
+            McVersionKind::Snapshot { year, week, suffix } => (0, *year, *week, suffix),
+            McVersionKind::PreRelease(_, n) => (1, *n, 0, ""),
+            McVersionKind::ReleaseCandidate(_, n) => (2, *n, 0, ""),
+            _ => (3, 0, 0, ""),
+        }
+    }
+}
+
+impl std::fmt::Display for McVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+impl PartialOrd for McVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+//没有发布时间时按版本号推断的先后顺序
+impl Ord for McVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ordering = self.era().cmp(&other.era());
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        let ordering = match (&self.kind, &other.kind) {
+            (McVersionKind::PreClassic(a), McVersionKind::PreClassic(b)) => a.cmp(b),
+            (McVersionKind::Classic(a), McVersionKind::Classic(b))
+            | (McVersionKind::Indev(a), McVersionKind::Indev(b))
+            | (McVersionKind::Infdev(a), McVersionKind::Infdev(b))
+            | (McVersionKind::Alpha(a), McVersionKind::Alpha(b))
+            | (McVersionKind::Beta(a), McVersionKind::Beta(b)) => a.cmp(b),
+            (McVersionKind::Other, McVersionKind::Other) => Ordering::Equal,
+            _ => {
+                //超出快照表的快照比所有已知正式版都新
+                let a = self.release_target();
+                let b = other.release_target();
+                match (a, b) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (None, Some(_)) => Ordering::Greater,
+                    (Some(_), None) => Ordering::Less,
+                    (None, None) => Ordering::Equal,
+                }
+                .then_with(|| self.stage().cmp(&other.stage()))
+            }
+        };
+        ordering.then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+fn parse_kind(id: &str) -> Option<McVersionKind> {
+    if let Some(release) = parse_release(id) {
+        return Some(McVersionKind::Release(release));
+    }
+    if let Some(snapshot) = parse_snapshot(id) {
+        return Some(snapshot);
+    }
+    //1.21-pre3, 1.14 Pre-Release 1, 1.21-rc1
+    for (separator, is_rc) in [("-pre", false), (" Pre-Release ", false), ("-rc", true)] {
+        if let Some((release, n)) = id.split_once(separator) {
+            let (Some(release), Ok(n)) = (parse_release(release), n.parse()) else {
+                continue;
+            };
+            return Some(if is_rc {
+                McVersionKind::ReleaseCandidate(release, n)
+            } else {
+                McVersionKind::PreRelease(release, n)
+            });
+        }
+    }
+    if let Some(n) = id.strip_prefix("rd-") {
+        return n.parse().ok().map(McVersionKind::PreClassic);
+    }
+    let old = |rest: &str| {
+        rest.starts_with(|ch: char| ch.is_ascii_digit())
+            .then(|| Version::from_string(&rest.to_string(), None).ok())
+            .flatten()
+    };
+    if let Some(rest) = id.strip_prefix("inf-") {
+        return old(rest).map(McVersionKind::Infdev);
+    }
+    if let Some(rest) = id.strip_prefix("in-") {
+        return old(rest).map(McVersionKind::Indev);
+    }
+    let mut chars = id.chars();
+    let kind = match chars.next()? {
+        'b' => McVersionKind::Beta(old(chars.as_str())?),
+        'a' => McVersionKind::Alpha(old(chars.as_str())?),
+        'c' => McVersionKind::Classic(old(chars.as_str())?),
+        _ => return None,
+    };
+    Some(kind)
+}
+
+//只由数字和点组成, 如 1.20.4
+fn parse_release(id: &str) -> Option<Version> {
+    let parts: Vec<&str> = id.split('.').collect();
+    if parts.len() < 2
+        || parts.len() > 3
+        || !parts
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|ch| ch.is_ascii_digit()))
+    {
+        return None;
+    }
+    Version::from_string(&id.to_string(), None).ok()
+}
+
+fn parse_snapshot(id: &str) -> Option<McVersionKind> {
+    let (year, rest) = id.split_once('w')?;
+    if year.len() != 2 || rest.len() < 3 {
+        return None;
+    }
+    let (week, suffix) = rest.split_at(2);
+    if !suffix.starts_with(|ch: char| ch.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(McVersionKind::Snapshot {
+        year: year.parse().ok()?,
+        week: week.parse().ok()?,
+        suffix: suffix.to_string(),
+    })
+}
+
+fn snapshot_target(year: u32, week: u32) -> Option<Version> {
+    let index = SNAPSHOT_SERIES.partition_point(|&(y, w, _)| (y, w) <= (year, week));
+    if index == 0 || index == SNAPSHOT_SERIES.len() && !in_last_series(year, week) {
+        return None;
+    }
+    Version::from_string(&SNAPSHOT_SERIES[index - 1].2.to_string(), None).ok()
+}
+
+//最后一个系列只认同一年内的快照, 更新的快照不知道属于哪个版本
+fn in_last_series(year: u32, week: u32) -> bool {
+    let &(last_year, last_week, _) = SNAPSHOT_SERIES.last().unwrap();
+    year == last_year && week <= last_week + 10
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        Version::from_string(&s.to_string(), None).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            McVersion::parse("1.20.4").kind,
+            McVersionKind::Release(v("1.20.4"))
+        );
+        assert_eq!(
+            McVersion::parse("24w14a").kind,
+            McVersionKind::Snapshot {
+                year: 24,
+                week: 14,
+                suffix: "a".to_string()
+            }
+        );
+        assert_eq!(
+            McVersion::parse("1.21-pre3").kind,
+            McVersionKind::PreRelease(v("1.21"), 3)
+        );
+        assert_eq!(
+            McVersion::parse("1.14 Pre-Release 2").kind,
+            McVersionKind::PreRelease(v("1.14"), 2)
+        );
+        assert_eq!(
+            McVersion::parse("1.21-rc1").kind,
+            McVersionKind::ReleaseCandidate(v("1.21"), 1)
+        );
+        assert_eq!(McVersion::parse("1.RV-Pokemon").kind, McVersionKind::Other);
+        assert_eq!(
+            McVersion::parse("b1.7.3").kind,
+            McVersionKind::Beta(v("1.7.3"))
+        );
+        assert_eq!(
+            McVersion::parse("a1.2.6").kind,
+            McVersionKind::Alpha(v("1.2.6"))
+        );
+        assert_eq!(
+            McVersion::parse("rd-132211").kind,
+            McVersionKind::PreClassic(132211)
+        );
+        assert!(matches!(
+            McVersion::parse("c0.30_01c").kind,
+            McVersionKind::Classic(_)
+        ));
+        assert!(matches!(
+            McVersion::parse("inf-20100618").kind,
+            McVersionKind::Infdev(_)
+        ));
+        assert!(matches!(
+            McVersion::parse("24w14potato").kind,
+            McVersionKind::Snapshot { .. }
+        ));
+        assert_eq!(McVersion::parse("1.RV-Pokemon").to_string(), "1.RV-Pokemon");
+        assert_eq!(McVersion::parse("beta").kind, McVersionKind::Other);
+        assert_eq!(McVersion::parse("1.2.3.4").kind, McVersionKind::Other);
+    }
+
+    #[test]
+    fn test_order_without_times() {
+        let ordered = [
+            "rd-132211",
+            "c0.0.11a",
+            "c0.30_01c",
+            "inf-20100618",
+            "a1.0.4",
+            "a1.2.6",
+            "b1.7.3",
+            "1.0",
+            "1.8.9",
+            "15w31a",
+            "1.9",
+            "1.19.4",
+            "23w12a",
+            "1.20",
+            "1.20.4",
+            "23w51a",
+            "24w14a",
+            "1.20.5-pre1",
+            "1.20.5-rc1",
+            "1.20.5",
+            "24w18a",
+            "1.21-pre3",
+            "1.21-rc1",
+            "1.21",
+            "1.RV-Pokemon",
+        ];
+        let mut versions: Vec<McVersion> =
+            ordered.iter().rev().map(|s| McVersion::parse(s)).collect();
+        versions.sort();
+        let sorted: Vec<&str> = versions.iter().map(|v| v.id()).collect();
+        assert_eq!(sorted, ordered);
+    }
+
+    #[test]
+    fn test_order_with_times() {
+        let time = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().to_utc();
+        let times: ReleaseTimes = [
+            ("1.9", "2016-02-29T13:49:54+00:00"),
+            ("1.RV-Pokemon", "2016-03-31T12:00:00+00:00"),
+            ("1.9.2", "2016-03-30T15:23:55+00:00"),
+            ("1.9.3", "2016-05-10T10:17:16+00:00"),
+        ]
+        .into_iter()
+        .map(|(id, t)| (id.to_string(), time(t)))
+        .collect();
+
+        let mut versions: Vec<McVersion> = ["1.9.3", "1.RV-Pokemon", "1.9", "1.9.2"]
+            .iter()
+            .map(|s| McVersion::parse(s))
+            .collect();
+        McVersion::sort_chronological(&mut versions, Some(&times));
+        let sorted: Vec<&str> = versions.iter().map(|v| v.id()).collect();
+        assert_eq!(sorted, ["1.9", "1.9.2", "1.RV-Pokemon", "1.9.3"]);
+
+        McVersion::sort_chronological(&mut versions, None);
+        let sorted: Vec<&str> = versions.iter().map(|v| v.id()).collect();
+        assert_eq!(sorted, ["1.9", "1.9.2", "1.9.3", "1.RV-Pokemon"]);
+    }
+
+    mod prop {
+        use proptest::prelude::*;
+
+        use super::*;
+
+        const IDS: &[&str] = &[
+            "1.9",
+            "1.9.2",
+            "1.RV-Pokemon",
+            "1.9.3",
+            "16w20a",
+            "1.10",
+            "1.10-pre1",
+            "b1.7.3",
+            "24w14potato",
+            "1.20.5",
+        ];
+
+        fn times() -> impl Strategy<Value = ReleaseTimes> {
+            proptest::collection::btree_map(0..IDS.len(), 0..1000_i64, 0..IDS.len()).prop_map(
+                |times| {
+                    times
+                        .into_iter()
+                        .map(|(i, t)| {
+                            let time = DateTime::from_timestamp(t * 86400, 0).unwrap();
+                            (IDS[i].to_string(), time)
+                        })
+                        .collect()
+                },
+            )
+        }
+
+        fn versions() -> impl Strategy<Value = Vec<McVersion>> {
+            proptest::collection::vec(proptest::sample::select(IDS), 0..12)
+                .prop_map(|ids| ids.into_iter().map(McVersion::parse).collect())
+        }
+
+        proptest! {
+            //一部分版本有时间, 一部分没有
+            #[test]
+            fn sort_with_partial_times(versions in versions(), times in times()) {
+                let mut sorted = versions.clone();
+                McVersion::sort_chronological(&mut sorted, Some(&times));
+
+                let mut reversed = versions.clone();
+                reversed.reverse();
+                McVersion::sort_chronological(&mut reversed, Some(&times));
+                prop_assert_eq!(&sorted, &reversed);
+
+                let mut expected = versions.clone();
+                expected.sort();
+                let mut actual = sorted.clone();
+                actual.sort();
+                prop_assert_eq!(actual, expected);
+
+                let known: Vec<_> = sorted.iter().filter_map(|v| times.get(v.id())).collect();
+                prop_assert!(known.windows(2).all(|w| w[0] <= w[1]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_belongs_to() {
+        let belongs = |a: &str, b: &str| McVersion::parse(a).belongs_to(&McVersion::parse(b));
+        assert!(belongs("24w14a", "1.20.5"));
+        assert!(belongs("24w14a", "1.20"));
+        assert!(!belongs("24w14a", "1.21"));
+        assert!(!belongs("24w14a", "1.20.4"));
+        assert!(belongs("23w51a", "1.20.5"));
+        assert!(belongs("24w18a", "1.21"));
+        assert!(belongs("1.21-pre3", "1.21"));
+        assert!(belongs("1.21-rc1", "1.21"));
+        assert!(belongs("1.20.4", "1.20"));
+        assert!(!belongs("1.20.4", "1.20.5"));
+        assert!(!belongs("b1.7.3", "1.7"));
+        assert!(!belongs("24w14a", "23w51a"));
+        //超出已知范围的快照
+        assert!(McVersion::parse("30w01a").release_target().is_none());
+        assert!(McVersion::parse("10w01a").release_target().is_none());
+    }
+}
@@ -1,2 +1,14 @@
+pub mod classpath;
 pub mod dirs;
+pub mod download;
+pub mod download_manager;
+pub mod java_runtime;
+pub mod launch;
+pub mod mc_version;
+pub mod rules;
+#[cfg(test)]
+pub mod test_server;
 pub mod version;
+pub mod version_json;
+pub mod version_manifest;
+pub mod version_req;
@@ -0,0 +1,467 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::version_json::{Argument, Library, Rule, RuleAction, VersionJson};
+
+//规则中的功能名, 传给 Environment::with_feature
+//启动时还不支持试玩账号, 自定义分辨率和快速游戏, 所有功能都视为 false, 目前只有测试使用
+#[allow(dead_code)]
+pub mod features {
+    pub const IS_DEMO_USER: &str = "is_demo_user";
+    pub const HAS_CUSTOM_RESOLUTION: &str = "has_custom_resolution";
+    pub const HAS_QUICK_PLAYS_SUPPORT: &str = "has_quick_plays_support";
+    pub const IS_QUICK_PLAY_SINGLEPLAYER: &str = "is_quick_play_singleplayer";
+    pub const IS_QUICK_PLAY_MULTIPLAYER: &str = "is_quick_play_multiplayer";
+    pub const IS_QUICK_PLAY_REALMS: &str = "is_quick_play_realms";
+}
+
+//版本 json 中的系统名
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OsName {
+    Windows,
+    Osx,
+    Linux,
+}
+
+impl OsName {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OsName::Windows => "windows",
+            OsName::Osx => "osx",
+            OsName::Linux => "linux",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Arch {
+    X86,
+    X86_64,
+    Arm,
+    Arm64,
+}
+
+impl Arch {
+    //规则中的 os.arch, 目前只用到 x86 表示 32 位
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Arch::X86 => "x86",
+            Arch::X86_64 => "x86_64",
+            Arch::Arm => "arm",
+            Arch::Arm64 => "arm64",
+        }
+    }
+
+    //natives 中 ${arch} 替换成位数
+    pub fn bits(&self) -> &'static str {
+        match self {
+            Arch::X86 | Arch::Arm => "32",
+            Arch::X86_64 | Arch::Arm64 => "64",
+        }
+    }
+}
+
+//判断规则时使用的环境, 测试中可以模拟其它系统
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Environment {
+    pub os: OsName,
+    pub arch: Arch,
+    //和 java 的 os.version 相同, 如 windows 10 为 10.0
+    pub os_version: String,
+    //没有列出的功能视为 false
+    #[serde(default)]
+    pub features: BTreeMap<String, bool>,
+}
+
+//旧版本没有 arguments.jvm 时官方启动器使用的参数
+const LEGACY_JVM_ARGUMENTS: &str = r#"[
+    { "rules": [{ "action": "allow", "os": { "name": "osx" } }], "value": ["-XstartOnFirstThread"] },
+    { "rules": [{ "action": "allow", "os": { "name": "windows" } }],
+      "value": "-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump" },
+    { "rules": [{ "action": "allow", "os": { "name": "windows", "version": "^10\\." } }],
+      "value": ["-Dos.name=Windows 10", "-Dos.version=10.0"] },
+    "-Djava.library.path=${natives_directory}",
+    "-Dminecraft.launcher.brand=${launcher_name}",
+    "-Dminecraft.launcher.version=${launcher_version}",
+    "-cp",
+    "${classpath}"
+]"#;
+
+impl Environment {
+    pub fn new(os: OsName, arch: Arch, os_version: &str) -> Self {
+        Environment {
+            os,
+            arch,
+            os_version: os_version.to_string(),
+            features: BTreeMap::new(),
+        }
+    }
+
+    //当前系统, 不支持的系统返回 None
+    pub fn current() -> Option<Self> {
+        let os = match std::env::consts::OS {
+            "windows" => OsName::Windows,
+            "macos" => OsName::Osx,
+            "linux" => OsName::Linux,
+            _ => return None,
+        };
+        let arch = match std::env::consts::ARCH {
+            "x86" => Arch::X86,
+            "x86_64" => Arch::X86_64,
+            "arm" => Arch::Arm,
+            "aarch64" => Arch::Arm64,
+            _ => return None,
+        };
+        Some(Environment::new(os, arch, &current_os_version()))
+    }
+
+    pub fn with_feature(mut self, feature: &str, enabled: bool) -> Self {
+        self.features.insert(feature.to_string(), enabled);
+        self
+    }
+
+    //没有规则时允许, 否则默认不允许, 由最后一条匹配的规则决定
+    pub fn allows(&self, rules: &[Rule]) -> bool {
+        if rules.is_empty() {
+            return true;
+        }
+        let mut action = RuleAction::Disallow;
+        for rule in rules {
+            if self.matches(rule) {
+                action = rule.action;
+            }
+        }
+        action == RuleAction::Allow
+    }
+
+    fn matches(&self, rule: &Rule) -> bool {
+        if let Some(os) = &rule.os {
+            if os
+                .name
+                .as_ref()
+                .is_some_and(|name| name != self.os.as_str())
+            {
+                return false;
+            }
+            if os
+                .arch
+                .as_ref()
+                .is_some_and(|arch| arch != self.arch.as_str())
+            {
+                return false;
+            }
+            //错误的正则表达式视为不匹配
+            if let Some(version) = &os.version {
+                match Regex::new(version) {
+                    Ok(regex) if regex.is_match(&self.os_version) => {}
+                    _ => return false,
+                }
+            }
+        }
+        if let Some(features) = &rule.features {
+            let enabled = |name: &String| self.features.get(name).copied().unwrap_or(false);
+            if features.iter().any(|(name, value)| enabled(name) != *value) {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn allows_library(&self, library: &Library) -> bool {
+        if !self.allows(library.rules.as_deref().unwrap_or_default()) {
+            return false;
+        }
+        //旧版本的本地库没有当前系统的文件时跳过
+        library.natives.is_none() || self.native_classifier(library).is_some()
+    }
+
+    //旧版本本地库在当前系统的 classifier
+    pub fn native_classifier(&self, library: &Library) -> Option<String> {
+        let classifier = library.natives.as_ref()?.get(self.os.as_str())?;
+        Some(classifier.replace("${arch}", self.arch.bits()))
+    }
+
+    //保持版本 json 中的顺序
+    pub fn libraries<'a>(&self, version: &'a VersionJson) -> Vec<&'a Library> {
+        version
+            .libraries
+            .iter()
+            .filter(|library| self.allows_library(library))
+            .collect()
+    }
+
+    pub fn arguments(&self, arguments: &[Argument]) -> Vec<String> {
+        let mut result = Vec::new();
+        for argument in arguments {
+            match argument {
+                Argument::Plain(value) => result.push(value.clone()),
+                Argument::Conditional { rules, value } => {
+                    if self.allows(rules) {
+                        result.extend(value.values().iter().cloned());
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    //旧版本的 minecraftArguments 按空格分隔
+    pub fn game_arguments(&self, version: &VersionJson) -> Vec<String> {
+        match (&version.arguments, &version.minecraft_arguments) {
+            (Some(arguments), _) if !arguments.game.is_empty() => self.arguments(&arguments.game),
+            (_, Some(legacy)) => legacy.split_whitespace().map(String::from).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn jvm_arguments(&self, version: &VersionJson) -> Vec<String> {
+        match &version.arguments {
+            Some(arguments) if !arguments.jvm.is_empty() => self.arguments(&arguments.jvm),
+            _ => {
+                let legacy: Vec<Argument> =
+                    serde_json::from_str(LEGACY_JVM_ARGUMENTS).expect("invalid legacy arguments");
+                self.arguments(&legacy)
+            }
+        }
+    }
+}
+
+//macos 上 java 的 os.version 是系统版本而不是内核版本
+#[cfg(unix)]
+fn current_os_version() -> String {
+    let (program, arg) = if cfg!(target_os = "macos") {
+        ("sw_vers", "-productVersion")
+    } else {
+        ("uname", "-r")
+    };
+    std::process::Command::new(program)
+        .arg(arg)
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default()
+}
+
+//ver 的输出形如 Microsoft Windows [Version 10.0.19045.3693], 取前两段
+#[cfg(windows)]
+fn current_os_version() -> String {
+    let output = std::process::Command::new("cmd")
+        .args(["/C", "ver"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .unwrap_or_default();
+    output
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .find(|part| part.contains('.'))
+        .map(|version| version.split('.').take(2).collect::<Vec<_>>().join("."))
+        .unwrap_or_default()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn current_os_version() -> String {
+    String::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn version(id: &str) -> VersionJson {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/versions");
+        VersionJson::resolve(&dir, id).unwrap()
+    }
+
+    fn names(libraries: Vec<&Library>) -> Vec<&str> {
+        libraries.into_iter().map(|l| l.name.as_str()).collect()
+    }
+
+    const JVM_COMMON: [&str; 8] = [
+        "-Djava.library.path=${natives_directory}",
+        "-Djna.tmpdir=${natives_directory}",
+        "-Dorg.lwjgl.system.SharedLibraryExtractPath=${natives_directory}",
+        "-Dio.netty.native.workdir=${natives_directory}",
+        "-Dminecraft.launcher.brand=${launcher_name}",
+        "-Dminecraft.launcher.version=${launcher_version}",
+        "-cp",
+        "${classpath}",
+    ];
+
+    #[test]
+    fn test_rules() {
+        let linux = Environment::new(OsName::Linux, Arch::X86_64, "6.1.0");
+        let allow = |os: serde_json::Value| -> Vec<Rule> {
+            serde_json::from_value(serde_json::json!([{ "action": "allow", "os": os }])).unwrap()
+        };
+        assert!(linux.allows(&[]));
+        assert!(linux.allows(&allow(serde_json::json!({ "name": "linux" }))));
+        assert!(!linux.allows(&allow(serde_json::json!({ "name": "osx" }))));
+        assert!(!linux.allows(&allow(serde_json::json!({ "arch": "x86" }))));
+        assert!(linux.allows(&allow(serde_json::json!({ "version": "^6\\." }))));
+        assert!(!linux.allows(&allow(serde_json::json!({ "version": "^5\\." }))));
+        assert!(!linux.allows(&allow(serde_json::json!({ "version": "(" }))));
+
+        //最后一条匹配的规则生效
+        let rules: Vec<Rule> = serde_json::from_value(serde_json::json!([
+            { "action": "allow" },
+            { "action": "disallow", "os": { "name": "osx" } },
+        ]))
+        .unwrap();
+        assert!(linux.allows(&rules));
+        assert!(!Environment::new(OsName::Osx, Arch::Arm64, "14.2").allows(&rules));
+
+        let rules: Vec<Rule> = serde_json::from_value(serde_json::json!([
+            { "action": "allow", "features": { "is_demo_user": true } },
+        ]))
+        .unwrap();
+        assert!(!linux.allows(&rules));
+        assert!(linux
+            .clone()
+            .with_feature(features::IS_DEMO_USER, true)
+            .allows(&rules));
+        assert!(!linux
+            .with_feature(features::IS_DEMO_USER, false)
+            .allows(&rules));
+    }
+
+    #[test]
+    fn test_modern_version() {
+        let version = version("1.20.1");
+        let linux = Environment::new(OsName::Linux, Arch::X86_64, "6.1.0");
+        assert_eq!(
+            names(linux.libraries(&version)),
+            vec![
+                "com.github.oshi:oshi-core:6.2.2",
+                "com.google.guava:guava:31.1-jre",
+                "com.mojang:authlib:4.0.43",
+                "org.lwjgl:lwjgl:3.3.1",
+                "org.lwjgl:lwjgl:3.3.1:natives-linux",
+            ]
+        );
+        assert_eq!(linux.jvm_arguments(&version), JVM_COMMON);
+        let game = linux.game_arguments(&version);
+        assert_eq!(game.len(), 22);
+        assert_eq!(game.last().unwrap(), "${version_type}");
+
+        let windows = Environment::new(OsName::Windows, Arch::X86, "10.0");
+        assert_eq!(
+            names(windows.libraries(&version))[3..],
+            [
+                "org.lwjgl:lwjgl:3.3.1",
+                "org.lwjgl:lwjgl:3.3.1:natives-windows",
+                "org.lwjgl:lwjgl:3.3.1:natives-windows-arm64",
+            ]
+        );
+        let jvm = windows.jvm_arguments(&version);
+        assert_eq!(
+            jvm[..4],
+            [
+                "-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump",
+                "-Dos.name=Windows 10",
+                "-Dos.version=10.0",
+                "-Xss1M",
+            ]
+        );
+        assert_eq!(jvm[4..], JVM_COMMON);
+        //windows 11 的 os.version 也是 10.0, 其它版本没有伪装参数
+        let windows_7 = Environment::new(OsName::Windows, Arch::X86_64, "6.1");
+        assert_eq!(windows_7.jvm_arguments(&version).len(), 9);
+
+        let osx = Environment::new(OsName::Osx, Arch::Arm64, "14.2")
+            .with_feature(features::HAS_CUSTOM_RESOLUTION, true)
+            .with_feature(features::IS_QUICK_PLAY_MULTIPLAYER, true);
+        assert_eq!(
+            names(osx.libraries(&version)),
+            vec![
+                "ca.weblite:java-objc-bridge:1.1",
+                "com.github.oshi:oshi-core:6.2.2",
+                "com.google.guava:guava:31.1-jre",
+                "com.mojang:authlib:4.0.43",
+                "org.lwjgl:lwjgl:3.3.1",
+                "org.lwjgl:lwjgl:3.3.1:natives-macos",
+                "org.lwjgl:lwjgl:3.3.1:natives-macos-arm64",
+            ]
+        );
+        assert_eq!(osx.jvm_arguments(&version)[0], "-XstartOnFirstThread");
+        assert_eq!(
+            osx.game_arguments(&version)[22..],
+            [
+                "--width",
+                "${resolution_width}",
+                "--height",
+                "${resolution_height}",
+                "--quickPlayMultiplayer",
+                "${quickPlayMultiplayer}",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_legacy_version() {
+        let version = version("1.12.2-forge-14.23.5.2860");
+        let linux = Environment::new(OsName::Linux, Arch::X86_64, "6.1.0");
+        let libraries = linux.libraries(&version);
+        assert_eq!(
+            names(libraries.clone())[5..],
+            [
+                "com.mojang:authlib:1.5.25",
+                "org.lwjgl.lwjgl:lwjgl:2.9.4-nightly-20150209",
+                "org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209",
+                "com.mojang:text2speech:1.10.3",
+                "com.mojang:text2speech:1.10.3",
+            ]
+        );
+        assert_eq!(
+            linux.native_classifier(libraries[7]).as_deref(),
+            Some("natives-linux")
+        );
+        let game = linux.game_arguments(&version);
+        assert_eq!(game[..2], ["--username", "${auth_player_name}"]);
+        assert_eq!(
+            game[game.len() - 4..],
+            [
+                "--tweakClass",
+                "net.minecraftforge.fml.common.launcher.FMLTweaker",
+                "--versionType",
+                "Forge",
+            ]
+        );
+        assert_eq!(
+            linux.jvm_arguments(&version),
+            [
+                "-Djava.library.path=${natives_directory}",
+                "-Dminecraft.launcher.brand=${launcher_name}",
+                "-Dminecraft.launcher.version=${launcher_version}",
+                "-cp",
+                "${classpath}",
+            ]
+        );
+
+        //osx 不使用 lwjgl 2 和 text2speech 的本地库, windows 额外使用 twitch 的本地库
+        let osx = Environment::new(OsName::Osx, Arch::X86_64, "10.15.7");
+        assert_eq!(osx.libraries(&version).len(), 7);
+        assert_eq!(osx.jvm_arguments(&version)[0], "-XstartOnFirstThread");
+        let windows = Environment::new(OsName::Windows, Arch::X86_64, "10.0");
+        let libraries = windows.libraries(&version);
+        assert_eq!(libraries.len(), 11);
+        assert_eq!(
+            windows.native_classifier(libraries[8]).as_deref(),
+            Some("natives-windows-64")
+        );
+        let windows_32 = Environment::new(OsName::Windows, Arch::X86, "6.1");
+        assert_eq!(
+            windows_32.native_classifier(libraries[8]).as_deref(),
+            Some("natives-windows-32")
+        );
+    }
+
+    #[test]
+    fn test_current() {
+        let current = Environment::current().unwrap();
+        assert!(current.features.is_empty());
+        assert_eq!(current.arch.bits().len(), 2);
+    }
+}
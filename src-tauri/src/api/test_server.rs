@@ -0,0 +1,224 @@
+//测试用的本地 http 服务, 不依赖网络
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+#[derive(Clone, Default)]
+struct Route {
+    body: Vec<u8>,
+    //额外的响应头, 设置了 ETag 或 Last-Modified 时支持条件请求
+    headers: Vec<(String, String)>,
+    faults: VecDeque<Fault>,
+}
+
+//注入的故障, 每个请求消耗一个, 用完后恢复正常响应
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    //只返回状态码, 没有响应体
+    Status(u16),
+    //Content-Length 还是完整的长度, 但只发送前 n 个字节就断开
+    Truncate(usize),
+}
+
+//收到的请求, 请求头的名字都是小写
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub path: String,
+    pub headers: HashMap<String, String>,
+}
+
+type Routes = Arc<Mutex<HashMap<String, Route>>>;
+type Requests = Arc<Mutex<Vec<Request>>>;
+
+pub struct TestServer {
+    addr: SocketAddr,
+    routes: Routes,
+    requests: Requests,
+}
+
+impl TestServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let routes: Routes = Arc::new(Mutex::new(HashMap::new()));
+        let requests: Requests = Arc::new(Mutex::new(Vec::new()));
+        let server_routes = routes.clone();
+        let server_requests = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let routes = server_routes.clone();
+                let requests = server_requests.clone();
+                thread::spawn(move || handle(stream, routes, requests));
+            }
+        });
+        TestServer {
+            addr,
+            routes,
+            requests,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn set(&self, path: &str, body: Vec<u8>) {
+        self.set_with_headers(path, body, &[]);
+    }
+
+    pub fn set_with_headers(&self, path: &str, body: Vec<u8>, headers: &[(&str, &str)]) {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self.routes.lock().unwrap().insert(
+            path.to_string(),
+            Route {
+                body,
+                headers,
+                faults: VecDeque::new(),
+            },
+        );
+    }
+
+    //接下来的请求依次使用这些故障, 需要先 set
+    pub fn inject(&self, path: &str, faults: &[Fault]) {
+        if let Some(route) = self.routes.lock().unwrap().get_mut(path) {
+            route.faults.extend(faults.iter().cloned());
+        }
+    }
+
+    pub fn remove(&self, path: &str) {
+        self.routes.lock().unwrap().remove(path);
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn handle(mut stream: TcpStream, routes: Routes, requests: Requests) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) if line.trim().is_empty() => break,
+            Ok(_) => {
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+                }
+            }
+        }
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let (route, fault) = {
+        let mut routes = routes.lock().unwrap();
+        let fault = routes.get_mut(path).and_then(|r| r.faults.pop_front());
+        (routes.get(path).cloned(), fault)
+    };
+    let range = headers.get("range").and_then(|r| range_start(r));
+    requests.lock().unwrap().push(Request {
+        path: path.to_string(),
+        headers: headers.clone(),
+    });
+
+    let (mut status, mut route) = match route {
+        Some(route) => match fault {
+            Some(Fault::Status(status)) => (status, route),
+            _ if not_modified(&route, &headers) => (304, route),
+            _ => (200, route),
+        },
+        None => (404, Route::default()),
+    };
+    //304 和错误状态没有响应体
+    let mut body = Vec::new();
+    if status == 200 {
+        let len = route.body.len();
+        match range {
+            Some(start) if start >= len => {
+                status = 416;
+                route
+                    .headers
+                    .push(("Content-Range".into(), format!("bytes */{}", len)));
+            }
+            Some(start) => {
+                status = 206;
+                route.headers.push((
+                    "Content-Range".into(),
+                    format!("bytes {}-{}/{}", start, len - 1, len),
+                ));
+                body = route.body[start..].to_vec();
+            }
+            None => body = route.body,
+        }
+    }
+    let content_length = body.len();
+    if let Some(Fault::Truncate(n)) = fault {
+        body.truncate(n);
+    }
+
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, reason(status));
+    for (name, value) in &route.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!(
+        "Accept-Ranges: bytes\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        content_length
+    ));
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(&body);
+    let _ = stream.flush();
+}
+
+//只支持 bytes=N- 这种断点续传用的形式
+fn range_start(range: &str) -> Option<usize> {
+    range
+        .strip_prefix("bytes=")?
+        .strip_suffix('-')?
+        .parse()
+        .ok()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        206 => "Partial Content",
+        304 => "Not Modified",
+        404 => "Not Found",
+        416 => "Range Not Satisfiable",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+fn not_modified(route: &Route, headers: &HashMap<String, String>) -> bool {
+    let header = |name: &str| {
+        route
+            .headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    };
+    match (header("ETag"), headers.get("if-none-match")) {
+        (Some(etag), Some(request)) => return etag == request,
+        //有 If-None-Match 时忽略 If-Modified-Since
+        (None, Some(_)) => return false,
+        _ => {}
+    }
+    matches!(
+        (header("Last-Modified"), headers.get("if-modified-since")),
+        (Some(modified), Some(request)) if modified == request
+    )
+}
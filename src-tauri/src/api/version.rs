@@ -1,23 +1,45 @@
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
 use serde::de;
-use serde::ser::SerializeStruct;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 
 #[derive(Debug, Clone)]
 pub struct Version {
     major: u32,
     minor: Option<u32>,
     patch: Option<u32>,
+    //patch 之后的数字, 如 java 的 11.0.2.1
+    extra: Vec<u32>,
     pre_release: Option<String>,
+    //+ 之后的构建信息, 不参与比较
+    build: Option<String>,
 }
 
 impl PartialEq for Version {
     fn eq(&self, other: &Self) -> bool {
-        self.major == other.major && self.minor == other.minor && self.patch == other.patch
+        self.major == other.major
+            && self.minor == other.minor
+            && self.patch == other.patch
+            && self.extra == other.extra
+            && self.pre_release == other.pre_release
     }
 }
 
 impl Eq for Version {}
 
+//与 Eq 保持一致, 忽略 build
+impl Hash for Version {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.major.hash(state);
+        self.minor.hash(state);
+        self.patch.hash(state);
+        self.extra.hash(state);
+        self.pre_release.hash(state);
+    }
+}
+
 impl PartialOrd for Version {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
@@ -47,7 +69,55 @@ impl Ord for Version {
             }
             return self.patch.unwrap().cmp(&other.patch.unwrap());
         }
-        return std::cmp::Ordering::Equal;
+        //11.0.2 < 11.0.2.1
+        if self.extra != other.extra {
+            return self.extra.cmp(&other.extra);
+        }
+        cmp_pre_release(self.pre_release.as_deref(), other.pre_release.as_deref())
+    }
+}
+
+//SemVer 2.0: 有预发布版本的更小, 按 . 分隔逐个比较
+fn cmp_pre_release(a: Option<&str>, b: Option<&str>) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => {
+            let mut a = a.split('.');
+            let mut b = b.split('.');
+            loop {
+                match (a.next(), b.next()) {
+                    (None, None) => return Ordering::Equal,
+                    (None, Some(_)) => return Ordering::Less,
+                    (Some(_), None) => return Ordering::Greater,
+                    (Some(a), Some(b)) => match cmp_identifier(a, b) {
+                        Ordering::Equal => continue,
+                        ordering => return ordering,
+                    },
+                }
+            }
+        }
+    }
+}
+
+//纯数字按数值比较且小于字母数字, 其余按 ASCII 比较
+fn cmp_identifier(a: &str, b: &str) -> Ordering {
+    let is_numeric = |s: &str| !s.is_empty() && s.chars().all(|ch| ch.is_ascii_digit());
+    match (is_numeric(a), is_numeric(b)) {
+        (true, true) => {
+            let a_trimmed = a.trim_start_matches('0');
+            let b_trimmed = b.trim_start_matches('0');
+            a_trimmed
+                .len()
+                .cmp(&b_trimmed.len())
+                .then_with(|| a_trimmed.cmp(b_trimmed))
+                //数值相同时(如 01 和 1)再按原字符串比较, 保证与 Eq 一致
+                .then_with(|| a.cmp(b))
+        }
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.cmp(b),
     }
 }
 
@@ -58,11 +128,17 @@ impl std::fmt::Display for Version {
             write!(f, ".{}", minor)?;
             if let Some(patch) = self.patch {
                 write!(f, ".{}", patch)?;
+                for n in &self.extra {
+                    write!(f, ".{}", n)?;
+                }
             }
         }
         if let Some(pre_release) = &self.pre_release {
             write!(f, " {}", pre_release)?;
         }
+        if let Some(build) = &self.build {
+            write!(f, "+{}", build)?;
+        }
         Ok(())
     }
 }
@@ -72,9 +148,7 @@ impl Serialize for Version {
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_struct("Version", 1)?;
-        s.serialize_field("value", &self.to_string())?;
-        s.end()
+        serializer.serialize_str(&self.to_semver_string())
     }
 }
 
@@ -83,22 +157,110 @@ impl<'de> Deserialize<'de> for Version {
     where
         D: serde::Deserializer<'de>,
     {
-        const FIELDS: &[&str] = &["value"];
-        deserializer.deserialize_struct("Version", FIELDS, VersionVisitor)
+        //既可以是字符串, 也可以是旧格式的 {"value": "..."}
+        deserializer.deserialize_any(VersionVisitor)
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Version::from_string(&s.to_string(), None)
+    }
+}
+
+impl TryFrom<&str> for Version {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
     }
 }
 
 impl Version {
+    //与 Display 不同, 预发布版本用 - 连接, 如 1.21-rc1, 可以被 from_string 原样解析回来
+    pub fn to_semver_string(&self) -> String {
+        let mut s = self.major.to_string();
+        if let Some(minor) = self.minor {
+            s.push_str(&format!(".{}", minor));
+            if let Some(patch) = self.patch {
+                s.push_str(&format!(".{}", patch));
+                for n in &self.extra {
+                    s.push_str(&format!(".{}", n));
+                }
+            }
+        }
+        if let Some(pre_release) = &self.pre_release {
+            s.push_str(&format!("-{}", pre_release));
+        }
+        if let Some(build) = &self.build {
+            s.push_str(&format!("+{}", build));
+        }
+        s
+    }
+
+    pub fn new(major: u32, minor: Option<u32>, patch: Option<u32>) -> Self {
+        Version {
+            major,
+            minor,
+            patch,
+            extra: Vec::new(),
+            pre_release: None,
+            build: None,
+        }
+    }
+
+    pub fn with_pre_release(mut self, pre_release: Option<String>) -> Self {
+        self.pre_release = pre_release;
+        self
+    }
+
+    //patch 之后的数字, 只有 patch 存在时才有意义
+    pub fn with_extra(mut self, extra: Vec<u32>) -> Self {
+        self.extra = extra;
+        self
+    }
+
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    pub fn minor(&self) -> Option<u32> {
+        self.minor
+    }
+
+    pub fn patch(&self) -> Option<u32> {
+        self.patch
+    }
+
+    pub fn pre_release(&self) -> Option<&str> {
+        self.pre_release.as_deref()
+    }
+
+    //缺少的 minor 和 patch 补 0, 1.20 与 1.20.0 视为相同
+    pub fn normalized(&self) -> Self {
+        Version {
+            major: self.major,
+            minor: Some(self.minor.unwrap_or(0)),
+            patch: Some(self.patch.unwrap_or(0)),
+            extra: self.extra.clone(),
+            pre_release: self.pre_release.clone(),
+            build: self.build.clone(),
+        }
+    }
+
     pub fn from_string(s: &String, ingore: Option<&Vec<char>>) -> Result<Self, String> {
         let mut index = 0;
         let mut start = false;
         let mut end = false;
         let mut v = vec![];
         let mut end_str = "".to_string();
-        for ch in s.chars() {
-            if ingore.is_some() && ingore.unwrap().contains(&ch) {
-                continue;
-            }
+        let mut chars = s
+            .chars()
+            .filter(|ch| !ingore.is_some_and(|ingore| ingore.contains(ch)))
+            .peekable();
+        while let Some(ch) = chars.next() {
             if end {
                 end_str.push(ch);
                 continue;
@@ -108,24 +270,39 @@ impl Version {
                     start = true;
                     v.push(0_u32);
                 }
-                v[index] = v[index] * 10 + (ch as u32 - '0' as u32);
+                //过长的数字无法放进 u32, 返回错误而不是溢出
+                v[index] = v[index]
+                    .checked_mul(10)
+                    .and_then(|n| n.checked_add(ch as u32 - '0' as u32))
+                    .ok_or_else(|| format!("Version number is too large: {}", s))?;
             } else {
+                let after_number = start;
                 if start {
                     index += 1;
                     start = false;
                 }
-                if index >= 3 {
+                //patch 之后用 . 连接的数字也是版本号的一部分, 如 11.0.2.1
+                let more_numbers = after_number
+                    && ch == '.'
+                    && chars.peek().is_some_and(|next| next.is_ascii_digit());
+                //紧跟在数字后的 - 或 + 开始预发布版本或构建信息, 如 1.0-rc1
+                if (index >= 3 && !more_numbers) || (after_number && (ch == '-' || ch == '+')) {
                     end = true;
                     end_str.push(ch);
                 }
             }
         }
-        let end_str = end_str.trim();
-        let pre_release = if end_str.is_empty() {
-            None
-        } else {
-            Some(end_str.to_string())
+        let (pre_release, build) = match end_str.split_once('+') {
+            Some((pre_release, build)) => (pre_release, Some(build)),
+            None => (end_str.as_str(), None),
         };
+        let pre_release = Some(pre_release.trim().trim_start_matches('-').trim())
+            .filter(|p| !p.is_empty())
+            .map(|p| p.to_string());
+        let build = build
+            .map(|b| b.trim())
+            .filter(|b| !b.is_empty())
+            .map(|b| b.to_string());
         let len = v.len();
         if len == 0 {
             return Err("Invalid version string".to_string());
@@ -134,16 +311,119 @@ impl Version {
             major: v[0],
             minor: None,
             patch: None,
-            pre_release: pre_release,
+            extra: Vec::new(),
+            pre_release,
+            build,
         };
         if len >= 2 {
             ver.minor = Some(v[1]);
         }
         if len >= 3 {
             ver.patch = Some(v[2]);
+            ver.extra = v[3..].to_vec();
         }
         Ok(ver)
     }
+
+    //按 java 的版本号规则解析, 1.8.0_281 => 8.0.281, 17.0.10+7 => 17.0.10, 21-ea => 21 ea
+    //可以直接传入 java -version 的第一行或 release 文件中的 JAVA_VERSION
+    pub fn from_java_string(s: &str) -> Result<Self, String> {
+        let token = s
+            .split(|ch: char| ch.is_whitespace() || ch == '"')
+            .find(|t| t.starts_with(|ch: char| ch.is_ascii_digit()))
+            .ok_or_else(|| format!("Invalid java version string `{}`", s))?;
+        //+ 之后是构建号, 不参与比较
+        let (token, build) = match token.split_once('+') {
+            Some((token, build)) => (token, Some(build.to_string()).filter(|b| !b.is_empty())),
+            None => (token, None),
+        };
+        let (numbers, pre_release) = match token.split_once('-') {
+            Some((numbers, pre_release)) => (numbers, Some(pre_release)),
+            None => (token, None),
+        };
+        //旧版的 -b09 是构建号而不是预发布版本
+        let pre_release = pre_release
+            .filter(|p| !(p.starts_with('b') && p[1..].chars().all(|ch| ch.is_ascii_digit())))
+            .filter(|p| !p.is_empty())
+            .map(|p| p.to_string());
+        //8u51 与 1.8.0_51 中的更新号
+        let (numbers, update) = match numbers.split_once(['_', 'u']) {
+            Some((numbers, update)) => (numbers, Some(parse_number(update, s)?)),
+            None => (numbers, None),
+        };
+        let mut parts = numbers
+            .split('.')
+            .map(|n| parse_number(n, s))
+            .collect::<Result<Vec<u32>, String>>()?;
+        let legacy = parts.len() >= 2 && parts[0] == 1;
+        if legacy {
+            parts.remove(0);
+        }
+
+        let mut ver = Version {
+            major: parts[0],
+            minor: parts.get(1).copied(),
+            patch: parts.get(2).copied(),
+            extra: parts.get(3..).unwrap_or_default().to_vec(),
+            pre_release,
+            build,
+        };
+        if legacy || update.is_some() {
+            ver.minor = Some(ver.minor.unwrap_or(0));
+            ver.patch = Some(update.unwrap_or(0));
+        }
+        Ok(ver)
+    }
+
+    //旧的 java 版本号用普通方式解析后是 1.8.0 _281, 转换成 8.0.281
+    //17.0.10 2024-01-16 LTS 中的发布日期和 LTS 不是预发布版本, 转换成构建信息
+    pub fn into_java(mut self) -> Self {
+        if let Some(suffix) = self.pre_release.as_deref().filter(|p| is_release_suffix(p)) {
+            let suffix = suffix.split_whitespace().collect::<Vec<_>>().join(".");
+            self.build = Some(match self.build.take() {
+                Some(build) => format!("{}.{}", suffix, build),
+                None => suffix,
+            });
+            self.pre_release = None;
+        }
+        if self.major != 1 || self.minor.is_none() {
+            return self;
+        }
+        let update = self
+            .pre_release
+            .as_deref()
+            .and_then(|p| p.strip_prefix('_'))
+            .and_then(|p| p.split(|ch: char| !ch.is_ascii_digit()).next())
+            .and_then(|p| p.parse().ok());
+        Version {
+            major: self.minor.unwrap_or(0),
+            minor: Some(0),
+            patch: Some(update.unwrap_or(0)),
+            extra: Vec::new(),
+            pre_release: None,
+            build: self.build,
+        }
+    }
+}
+
+//java -version 中版本号之后的发布日期和 LTS 标记, 如 2024-01-16 LTS
+fn is_release_suffix(suffix: &str) -> bool {
+    let is_date = |word: &str| {
+        let parts: Vec<&str> = word.split('-').collect();
+        parts.len() == 3
+            && parts
+                .iter()
+                .zip([4, 2, 2])
+                .all(|(part, len)| part.len() == len && part.chars().all(|ch| ch.is_ascii_digit()))
+    };
+    suffix
+        .split_whitespace()
+        .all(|word| word == "LTS" || is_date(word))
+}
+
+fn parse_number(n: &str, s: &str) -> Result<u32, String> {
+    n.parse()
+        .map_err(|_| format!("Invalid java version string `{}`", s))
 }
 
 struct VersionVisitor;
@@ -155,6 +435,13 @@ impl<'de> serde::de::Visitor<'de> for VersionVisitor {
         formatter.write_str("a version string")
     }
 
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        parse_value(value)
+    }
+
     fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
     where
         A: serde::de::MapAccess<'de>,
@@ -178,17 +465,20 @@ impl<'de> serde::de::Visitor<'de> for VersionVisitor {
         // 检查字段是否存在
         let value_str = value_opt.ok_or_else(|| de::Error::missing_field("value"))?;
 
-        // 调用 from_string，将错误 (semver::Error) 转换成 serde 的 Error
-        match Version::from_string(&value_str, None) {
-            Ok(ver) => Ok(ver),
-            Err(e) => Err(de::Error::custom(format!(
-                "failed to parse Version from string `{}`: {}",
-                value_str, e
-            ))),
-        }
+        parse_value(&value_str)
     }
 }
 
+// 调用 from_string，将错误转换成 serde 的 Error
+fn parse_value<E: de::Error>(value: &str) -> Result<Version, E> {
+    value.parse().map_err(|e| {
+        de::Error::custom(format!(
+            "failed to parse Version from string `{}`: {}",
+            value, e
+        ))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use crate::api::version::Version;
@@ -199,7 +489,9 @@ mod tests {
             major: 1,
             minor: Some(0),
             patch: Some(0),
+            extra: Vec::new(),
             pre_release: Some("alpha".to_string()),
+            build: None,
         };
         assert_eq!(version.to_string(), "1.0.0 alpha");
 
@@ -207,7 +499,9 @@ mod tests {
             major: 1,
             minor: Some(0),
             patch: None,
+            extra: Vec::new(),
             pre_release: Some("alpha".to_string()),
+            build: None,
         };
         assert_eq!(version.to_string(), "1.0 alpha");
 
@@ -215,7 +509,9 @@ mod tests {
             major: 1,
             minor: None,
             patch: Some(0),
+            extra: Vec::new(),
             pre_release: Some("alpha".to_string()),
+            build: None,
         };
         assert_eq!(version.to_string(), "1 alpha");
 
@@ -223,7 +519,9 @@ mod tests {
             major: 1,
             minor: Some(0),
             patch: Some(2),
+            extra: Vec::new(),
             pre_release: None,
+            build: None,
         };
         assert_eq!(version.to_string(), "1.0.2");
     }
@@ -234,27 +532,35 @@ mod tests {
             major: 1,
             minor: Some(0),
             patch: Some(0),
+            extra: Vec::new(),
             pre_release: Some("alpha".to_string()),
+            build: None,
         };
         let version2 = super::Version {
             major: 1,
             minor: Some(0),
             patch: Some(0),
+            extra: Vec::new(),
             pre_release: None,
+            build: None,
         };
-        assert!(version1 == version2);
+        assert!(version1 < version2);
 
         let version1 = super::Version {
             major: 1,
             minor: Some(0),
             patch: Some(0),
+            extra: Vec::new(),
             pre_release: Some("alpha".to_string()),
+            build: None,
         };
         let version2 = super::Version {
             major: 1,
             minor: None,
             patch: Some(0),
+            extra: Vec::new(),
             pre_release: None,
+            build: None,
         };
         assert!(version1 > version2);
 
@@ -262,13 +568,17 @@ mod tests {
             major: 1,
             minor: Some(0),
             patch: Some(0),
+            extra: Vec::new(),
             pre_release: Some("alpha".to_string()),
+            build: None,
         };
         let version2 = super::Version {
             major: 1,
             minor: Some(0),
             patch: Some(10),
+            extra: Vec::new(),
             pre_release: None,
+            build: None,
         };
         assert!(version1 < version2);
     }
@@ -278,7 +588,9 @@ mod tests {
             major: 1,
             minor: Some(0),
             patch: Some(10),
+            extra: Vec::new(),
             pre_release: None,
+            build: None,
         };
         let version_str = version.to_string();
         assert!(Version::from_string(&version_str, None).unwrap() == version);
@@ -287,7 +599,9 @@ mod tests {
             major: 1,
             minor: Some(0),
             patch: Some(10),
+            extra: Vec::new(),
             pre_release: Some("s".to_string()),
+            build: None,
         };
         let version_str = "sad 1 sda0 d10   s".to_string();
         assert!(Version::from_string(&version_str, None).unwrap() == version);
@@ -296,7 +610,9 @@ mod tests {
             major: 1,
             minor: Some(0),
             patch: Some(10),
+            extra: Vec::new(),
             pre_release: Some("s".to_string()),
+            build: None,
         };
         let version_str = "sad 1 sda0 d10  \"  s".to_string();
         assert!(Version::from_string(&version_str, Some(&vec!['"'])).unwrap() == version);
@@ -305,7 +621,9 @@ mod tests {
             major: 1,
             minor: Some(0),
             patch: Some(10),
+            extra: Vec::new(),
             pre_release: Some("\"  s".to_string()),
+            build: None,
         };
         let version_str = "sad 1 sda0 d10  \"  s   ".to_string();
         assert!(Version::from_string(&version_str, None).unwrap() == version);
@@ -314,21 +632,273 @@ mod tests {
             major: 17,
             minor: Some(0),
             patch: Some(10),
+            extra: Vec::new(),
             pre_release: Some("2024-01-16 LTS".to_string()),
+            build: None,
         };
         let version_str = "openjdk version \"17.0.10\" 2024-01-16 LTS".to_string();
         assert!(Version::from_string(&version_str, Some(&vec!['"'])).unwrap() == version);
+
+        //超出 u32 的数字
+        assert!(Version::from_string(&"99999999999".to_string(), None).is_err());
+        assert!(Version::from_string(&"1.4294967296".to_string(), None).is_err());
+        assert_eq!(
+            Version::from_string(&"4294967295".to_string(), None)
+                .unwrap()
+                .major,
+            u32::MAX
+        );
     }
+    #[test]
+    fn semver_ord_test() {
+        let parse = |s: &str| Version::from_string(&s.to_string(), None).unwrap();
+        //SemVer 2.0 规范中的例子
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1-rc1",
+            "1.0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(parse(pair[0]) < parse(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+
+        let version = parse("1.0.0-rc.1+build.5");
+        assert_eq!(version.pre_release.as_deref(), Some("rc.1"));
+        assert_eq!(version.build.as_deref(), Some("build.5"));
+        assert_eq!(version.to_string(), "1.0.0 rc.1+build.5");
+        assert_eq!(parse(&version.to_string()).build, version.build);
+        assert_eq!(parse("1.0.0+20130313"), parse("1.0.0"));
+        assert_eq!(parse("1.0.0+20130313").pre_release, None);
+        assert_ne!(parse("1.0.0-rc1"), parse("1.0.0"));
+        assert_eq!(parse("1.20-pre1").patch, None);
+        assert_eq!(parse("1.20-pre1").pre_release.as_deref(), Some("pre1"));
+        assert_eq!(
+            Version::from_java_string("17.0.10+7")
+                .unwrap()
+                .build
+                .as_deref(),
+            Some("7")
+        );
+    }
+
+    mod prop {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        use proptest::prelude::*;
+
+        use super::super::Version;
+
+        fn version() -> impl Strategy<Value = Version> {
+            (
+                0..4_u32,
+                proptest::option::of(0..4_u32),
+                proptest::option::of(0..4_u32),
+                proptest::collection::vec(0..3_u32, 0..3),
+                proptest::option::of("(0|1|01|alpha|beta|rc)(\\.(0|1|2|11|alpha|x))?"),
+                proptest::option::of("[a-z0-9]{1,4}"),
+            )
+                .prop_map(|(major, minor, patch, extra, pre_release, build)| Version {
+                    major,
+                    minor,
+                    patch: minor.and(patch),
+                    extra: if minor.and(patch).is_some() {
+                        extra
+                    } else {
+                        Vec::new()
+                    },
+                    pre_release,
+                    build,
+                })
+        }
+
+        fn hash(version: &Version) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            version.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        proptest! {
+            #[test]
+            fn antisymmetric(a in version(), b in version()) {
+                prop_assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+            }
+
+            #[test]
+            fn transitive(a in version(), b in version(), c in version()) {
+                if a <= b && b <= c {
+                    prop_assert!(a <= c);
+                }
+            }
+
+            #[test]
+            fn eq_consistent_with_ord_and_hash(a in version(), b in version()) {
+                prop_assert_eq!(a == b, a.cmp(&b).is_eq());
+                if a == b {
+                    prop_assert_eq!(hash(&a), hash(&b));
+                }
+            }
+
+            #[test]
+            fn build_ignored(a in version(), build in "[a-z0-9]{1,4}") {
+                let mut b = a.clone();
+                b.build = Some(build);
+                prop_assert_eq!(&a, &b);
+                prop_assert_eq!(hash(&a), hash(&b));
+            }
+
+            #[test]
+            fn pre_release_lower_than_release(a in version()) {
+                let mut release = a.clone();
+                release.pre_release = None;
+                if a.pre_release.is_some() {
+                    prop_assert!(a < release);
+                }
+            }
+
+            #[test]
+            fn serde_round_trip(a in version()) {
+                let json = serde_json::to_string(&a).unwrap();
+                let parsed: Version = serde_json::from_str(&json).unwrap();
+                prop_assert_eq!(&parsed, &a);
+                prop_assert_eq!(parsed.build, a.build);
+            }
+
+            #[test]
+            fn from_string_never_panics(s in "[0-9.+ a-z-]{0,40}") {
+                let _ = Version::from_string(&s, None);
+            }
+
+            #[test]
+            fn display_round_trip(a in version()) {
+                let parsed = Version::from_string(&a.to_string(), None).unwrap();
+                if a.minor.is_some() && a.patch.is_some() {
+                    prop_assert_eq!(&parsed, &a);
+                    prop_assert_eq!(parsed.build, a.build);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn from_java_string_test() {
+        let java = |s: &str| Version::from_java_string(s).unwrap();
+        let version = |major, minor, patch, pre_release: Option<&str>| Version {
+            major,
+            minor,
+            patch,
+            extra: Vec::new(),
+            pre_release: pre_release.map(|p| p.to_string()),
+            build: None,
+        };
+
+        assert_eq!(
+            java("java version \"1.8.0_281\""),
+            version(8, Some(0), Some(281), None)
+        );
+        assert_eq!(java("1.8.0_281").to_string(), "8.0.281");
+        assert_eq!(java("1.8.0"), version(8, Some(0), Some(0), None));
+        assert_eq!(java("1.7.0_80-b15"), version(7, Some(0), Some(80), None));
+        assert_eq!(java("8u51"), version(8, Some(0), Some(51), None));
+        assert_eq!(
+            java("openjdk version \"17.0.10\" 2024-01-16 LTS"),
+            version(17, Some(0), Some(10), None)
+        );
+        assert_eq!(java("17.0.10+7"), version(17, Some(0), Some(10), None));
+        assert_eq!(java("21-ea"), version(21, None, None, Some("ea")));
+        assert_eq!(java("21-ea").to_string(), "21 ea");
+        assert_eq!(java("11.0.2.1").extra, [1]);
+        assert_eq!(java("11.0.2.1").to_string(), "11.0.2.1");
+        assert!(java("11.0.2.1") > java("11.0.2"));
+        assert!(java("11.0.2.1") < java("11.0.3"));
+        assert!(java("11.0.2.1") < java("11.0.2.2"));
+        let extra: Version = serde_json::from_str("\"11.0.2.1\"").unwrap();
+        assert_eq!(extra, java("11.0.2.1"));
+        assert_eq!(serde_json::to_string(&extra).unwrap(), "\"11.0.2.1\"");
+        assert_eq!(java("9"), version(9, None, None, None));
+        assert!(Version::from_java_string("openjdk").is_err());
+        assert!(Version::from_java_string("1.x.0").is_err());
+
+        assert!(java("1.8.0_281") < java("11.0.2"));
+        assert!(java("1.8.0_281") > java("1.8.0_51"));
+        assert!(java("17.0.10+7") < java("21-ea"));
+        assert_eq!(java("1.8.0_281").major(), 8);
+
+        let legacy = Version::from_string(&"1.8.0 _281".to_string(), None).unwrap();
+        assert_eq!(legacy.into_java(), java("1.8.0_281"));
+        assert_eq!(java("17.0.10").into_java(), java("17.0.10"));
+        //旧文件中保存的完整版本字符串
+        let dated = Version::from_string(&"17.0.10 2024-01-16 LTS".to_string(), None).unwrap();
+        assert!(dated < java("17.0.10"));
+        let dated = dated.into_java();
+        assert_eq!(dated, java("17.0.10"));
+        assert_eq!(dated.build.as_deref(), Some("2024-01-16.LTS"));
+        assert_eq!(dated.to_semver_string(), "17.0.10+2024-01-16.LTS");
+        let ea = Version::from_string(&"21-ea".to_string(), None).unwrap();
+        assert_eq!(ea.into_java(), java("21-ea"));
+    }
+
     #[test]
     fn de_test() {
         let version = super::Version {
             major: 17,
             minor: Some(0),
             patch: Some(10),
+            extra: Vec::new(),
             pre_release: Some("2024-01-16 LTS".to_string()),
+            build: None,
         };
         let json = serde_json::to_string(&version).unwrap();
         let deserialized: super::Version = serde_json::from_str(&json).unwrap();
         assert_eq!(deserialized, version);
     }
+
+    #[test]
+    fn serde_string_test() {
+        let version: Version = "17.0.10 2024-01-16 LTS".parse().unwrap();
+        let json = serde_json::to_string(&version).unwrap();
+        assert_eq!(json, "\"17.0.10-2024-01-16 LTS\"");
+        let deserialized: Version = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, version);
+
+        //旧格式
+        let legacy: Version =
+            serde_json::from_str(r#"{"value": "17.0.10 2024-01-16 LTS", "other": 1}"#).unwrap();
+        assert_eq!(legacy, version);
+        assert_eq!(serde_json::to_string(&legacy).unwrap(), json);
+
+        let with_build = Version::try_from("1.0.0-rc.1+build.5").unwrap();
+        let round_trip: Version =
+            serde_json::from_str(&serde_json::to_string(&with_build).unwrap()).unwrap();
+        assert_eq!(round_trip.to_string(), with_build.to_string());
+        assert_eq!(
+            serde_json::to_string(&with_build).unwrap(),
+            "\"1.0.0-rc.1+build.5\""
+        );
+
+        assert!(serde_json::from_str::<Version>("\"abc\"").is_err());
+        assert!(serde_json::from_str::<Version>(r#"{"other": "1.0"}"#).is_err());
+        assert!(serde_json::from_str::<Version>("1").is_err());
+        assert!("".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn map_key_test() {
+        let mut map = std::collections::HashMap::new();
+        map.insert(Version::try_from("1.20.4").unwrap(), "a".to_string());
+        map.insert(Version::try_from("1.21-rc1").unwrap(), "b".to_string());
+        let json = serde_json::to_string(&map).unwrap();
+        assert!(json.contains("\"1.20.4\":\"a\""));
+        let deserialized: std::collections::HashMap<Version, String> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, map);
+        assert_eq!(deserialized[&"1.20.4+b1".parse().unwrap()], "a");
+    }
 }
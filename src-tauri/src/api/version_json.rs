@@ -0,0 +1,441 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use super::version_manifest::VersionType;
+
+//versions/<id>/<id>.json, 加载器的版本通过 inheritsFrom 继承原版
+//时间使用字符串, 加载器写的时间格式不一定是 RFC 3339
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionJson {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherits_from: Option<String>,
+    //使用哪个版本的游戏 jar, 合并时设为最顶层的父版本
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jar: Option<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub version_type: Option<VersionType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub main_class: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Arguments>,
+    //1.13 之前的游戏参数, 空格分隔
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minecraft_arguments: Option<String>,
+    #[serde(default)]
+    pub libraries: Vec<Library>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_index: Option<AssetIndex>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assets: Option<String>,
+    //client, server, client_mappings 等
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub downloads: BTreeMap<String, Download>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub java_version: Option<JavaVersionInfo>,
+    //Forge 用空对象关闭原版的日志配置, 所以空对象和没有不同
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<BTreeMap<String, Logging>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compliance_level: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum_launcher_version: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Arguments {
+    #[serde(default)]
+    pub game: Vec<Argument>,
+    #[serde(default)]
+    pub jvm: Vec<Argument>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Argument {
+    Plain(String),
+    Conditional {
+        rules: Vec<Rule>,
+        value: ArgumentValue,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ArgumentValue {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl ArgumentValue {
+    pub fn values(&self) -> &[String] {
+        match self {
+            ArgumentValue::Single(value) => std::slice::from_ref(value),
+            ArgumentValue::Multiple(values) => values,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub action: RuleAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<OsRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<BTreeMap<String, bool>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    Allow,
+    Disallow,
+}
+
+//version 是正则表达式
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OsRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Library {
+    //maven 坐标 group:artifact:version[:classifier]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<LibraryDownloads>,
+    //Fabric 等加载器只给出 maven 仓库地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules: Option<Vec<Rule>>,
+    //旧版本的本地库, 系统名 => classifier, 其中可能有 ${arch}
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub natives: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extract: Option<Extract>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LibraryDownloads {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<Artifact>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub classifiers: BTreeMap<String, Artifact>,
+}
+
+//url 为空时文件由安装器生成, 不能下载
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Extract {
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetIndex {
+    pub id: String,
+    pub sha1: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Download {
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JavaVersionInfo {
+    pub component: String,
+    pub major_version: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Logging {
+    //其中的 ${path} 替换为日志配置文件的路径
+    pub argument: String,
+    pub file: LoggingFile,
+    #[serde(rename = "type")]
+    pub logging_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggingFile {
+    pub id: String,
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+}
+
+//inheritsFrom 最多嵌套的层数, 防止循环
+const MAX_INHERITANCE: usize = 16;
+
+impl VersionJson {
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    pub fn path(versions_dir: &Path, id: &str) -> PathBuf {
+        versions_dir.join(id).join(format!("{}.json", id))
+    }
+
+    pub fn read(versions_dir: &Path, id: &str) -> Result<Self> {
+        let path = Self::path(versions_dir, id);
+        let content = fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read `{}`: {}", path.display(), e))?;
+        Self::parse(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse `{}`: {}", path.display(), e))
+    }
+
+    //游戏 jar 对应的版本 id, 没有继承时就是自己
+    pub fn jar_id(&self) -> &str {
+        self.jar.as_deref().unwrap_or(&self.id)
+    }
+
+    //读取版本并合并整个 inheritsFrom 链
+    pub fn resolve(versions_dir: &Path, id: &str) -> Result<Self> {
+        Self::resolve_with(id, |id| Self::read(versions_dir, id))
+    }
+
+    pub fn resolve_with(id: &str, mut load: impl FnMut(&str) -> Result<Self>) -> Result<Self> {
+        let mut chain = vec![load(id)?];
+        while let Some(parent) = chain.last().and_then(|v| v.inherits_from.clone()) {
+            if chain.iter().any(|v| v.id == parent) {
+                bail!("Version `{}` inherits from itself through `{}`", id, parent);
+            }
+            if chain.len() >= MAX_INHERITANCE {
+                bail!("Version `{}` has too many parents", id);
+            }
+            chain.push(load(&parent)?);
+        }
+        let mut merged = chain.pop().unwrap_or_default();
+        while let Some(child) = chain.pop() {
+            merged = child.merge(merged);
+        }
+        Ok(merged)
+    }
+
+    //把父版本合并进来, 和官方启动器一致:
+    //单个值子版本优先, 库子版本在前, 新格式的参数父版本在前
+    pub fn merge(self, parent: VersionJson) -> VersionJson {
+        let arguments = match (parent.arguments, self.arguments) {
+            (Some(mut parent), Some(child)) => {
+                parent.game.extend(child.game);
+                parent.jvm.extend(child.jvm);
+                Some(parent)
+            }
+            (parent, child) => child.or(parent),
+        };
+        let mut libraries = self.libraries;
+        libraries.extend(parent.libraries);
+        let mut downloads = parent.downloads;
+        downloads.extend(self.downloads);
+        VersionJson {
+            id: self.id,
+            inherits_from: None,
+            jar: self.jar.or(parent.jar).or(Some(parent.id)),
+            version_type: self.version_type.or(parent.version_type),
+            main_class: self.main_class.or(parent.main_class),
+            arguments,
+            minecraft_arguments: self.minecraft_arguments.or(parent.minecraft_arguments),
+            libraries,
+            asset_index: self.asset_index.or(parent.asset_index),
+            assets: self.assets.or(parent.assets),
+            downloads,
+            java_version: self.java_version.or(parent.java_version),
+            logging: self.logging.or(parent.logging),
+            release_time: self.release_time.or(parent.release_time),
+            time: self.time.or(parent.time),
+            compliance_level: self.compliance_level.or(parent.compliance_level),
+            minimum_launcher_version: self
+                .minimum_launcher_version
+                .or(parent.minimum_launcher_version),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/versions")
+    }
+
+    //和 tests/fixtures/versions/merged 中的结果比较
+    fn assert_golden(id: &str) {
+        let merged = VersionJson::resolve(&fixtures(), id).unwrap();
+        let golden = fixtures().join("merged").join(format!("{}.json", id));
+        let expected: VersionJson =
+            serde_json::from_str(&fs::read_to_string(golden).unwrap()).unwrap();
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn test_parse_vanilla() {
+        let version = VersionJson::read(&fixtures(), "1.20.1").unwrap();
+        assert_eq!(
+            version.main_class.as_deref(),
+            Some("net.minecraft.client.main.Main")
+        );
+        assert_eq!(version.version_type, Some(VersionType::Release));
+        assert_eq!(version.java_version.as_ref().unwrap().major_version, 17);
+        assert_eq!(version.asset_index.as_ref().unwrap().id, "5");
+        assert_eq!(version.downloads["client"].size, 23028853);
+        assert_eq!(
+            version.logging.as_ref().unwrap()["client"].file.id,
+            "client-1.12.xml"
+        );
+
+        let arguments = version.arguments.as_ref().unwrap();
+        assert_eq!(arguments.game[0], Argument::Plain("--username".to_string()));
+        let Argument::Conditional { rules, value } = &arguments.game[23] else {
+            panic!("expected a conditional argument");
+        };
+        assert!(rules[0].features.as_ref().unwrap()["has_custom_resolution"]);
+        assert_eq!(value.values().len(), 4);
+        let Argument::Conditional { rules, .. } = &arguments.jvm[2] else {
+            panic!("expected a conditional argument");
+        };
+        assert_eq!(
+            rules[0].os.as_ref().unwrap().version.as_deref(),
+            Some("^10\\.")
+        );
+
+        //保存后再读取不丢失内容
+        let saved = serde_json::to_string(&version).unwrap();
+        assert_eq!(VersionJson::parse(&saved).unwrap(), version);
+    }
+
+    #[test]
+    fn test_parse_legacy() {
+        let version = VersionJson::read(&fixtures(), "1.12.2").unwrap();
+        assert!(version.arguments.is_none());
+        assert!(version
+            .minecraft_arguments
+            .as_deref()
+            .unwrap()
+            .starts_with("--username ${auth_player_name}"));
+        let platform = &version.libraries[3];
+        assert_eq!(platform.natives.as_ref().unwrap()["linux"], "natives-linux");
+        assert_eq!(
+            platform.extract.as_ref().unwrap().exclude,
+            vec!["META-INF/"]
+        );
+        let downloads = platform.downloads.as_ref().unwrap();
+        assert!(downloads.artifact.is_none());
+        assert_eq!(downloads.classifiers.len(), 3);
+    }
+
+    #[test]
+    fn test_resolve_fabric() {
+        assert_golden("fabric-loader-0.15.11-1.20.1");
+        let merged = VersionJson::resolve(&fixtures(), "fabric-loader-0.15.11-1.20.1").unwrap();
+        assert_eq!(
+            merged.main_class.as_deref(),
+            Some("net.fabricmc.loader.impl.launch.knot.KnotClient")
+        );
+        //加载器的库在前, 参数追加在原版之后
+        assert_eq!(merged.libraries[0].name, "org.ow2.asm:asm:9.6");
+        assert_eq!(merged.libraries.len(), 16);
+        let jvm = &merged.arguments.as_ref().unwrap().jvm;
+        assert_eq!(
+            jvm.last().unwrap(),
+            &Argument::Plain("-DFabricMcEmu= net.minecraft.client.main.Main ".to_string())
+        );
+        assert_eq!(merged.asset_index.as_ref().unwrap().id, "5");
+        assert_eq!(
+            merged.release_time.as_deref(),
+            Some("2024-05-05T14:14:43+0000")
+        );
+    }
+
+    #[test]
+    fn test_resolve_forge() {
+        assert_golden("1.12.2-forge-14.23.5.2860");
+        let merged = VersionJson::resolve(&fixtures(), "1.12.2-forge-14.23.5.2860").unwrap();
+        assert!(merged
+            .minecraft_arguments
+            .as_deref()
+            .unwrap()
+            .contains("--tweakClass net.minecraftforge.fml.common.launcher.FMLTweaker"));
+        //Forge 关闭了原版的日志配置
+        assert_eq!(merged.logging, Some(BTreeMap::new()));
+        assert_eq!(merged.java_version.as_ref().unwrap().major_version, 8);
+        assert_eq!(merged.downloads["client"].size, 10180113);
+        assert_eq!(merged.jar_id(), "1.12.2");
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let version = |id: &str, parent: Option<&str>| VersionJson {
+            id: id.to_string(),
+            inherits_from: parent.map(|p| p.to_string()),
+            ..Default::default()
+        };
+        let cycle = VersionJson::resolve_with("a", |id| {
+            Ok(match id {
+                "a" => version("a", Some("b")),
+                _ => version("b", Some("a")),
+            })
+        });
+        assert!(cycle.is_err());
+
+        //多层继承时每一层都合并
+        let chain = VersionJson::resolve_with("c", |id| {
+            let mut v = match id {
+                "c" => version("c", Some("b")),
+                "b" => version("b", Some("a")),
+                _ => version("a", None),
+            };
+            v.main_class = (id != "c").then(|| format!("{}.Main", id));
+            v.assets = (id == "a").then(|| "5".to_string());
+            Ok(v)
+        })
+        .unwrap();
+        assert_eq!(chain.id, "c");
+        assert_eq!(chain.inherits_from, None);
+        assert_eq!(chain.main_class.as_deref(), Some("b.Main"));
+        assert_eq!(chain.assets.as_deref(), Some("5"));
+
+        assert!(VersionJson::resolve(&fixtures(), "missing").is_err());
+    }
+}
@@ -0,0 +1,388 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use reqwest::{
+    blocking::Client,
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::atomic_file::write_atomic;
+
+use super::{download, mc_version::ReleaseTimes};
+
+pub const MOJANG_META_BASE: &str = "https://piston-meta.mojang.com";
+const MANIFEST_PATH: &str = "/mc/game/version_manifest_v2.json";
+const CACHE_FILE: &str = "version_manifest_v2.json";
+const CACHE_META_FILE: &str = "version_manifest_v2.meta.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionManifest {
+    pub latest: LatestVersions,
+    pub versions: Vec<ManifestVersion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatestVersions {
+    pub release: String,
+    pub snapshot: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestVersion {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub version_type: VersionType,
+    //版本 json 的地址
+    pub url: String,
+    pub time: DateTime<Utc>,
+    pub release_time: DateTime<Utc>,
+    pub sha1: String,
+    #[serde(default)]
+    pub compliance_level: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionType {
+    Release,
+    Snapshot,
+    OldBeta,
+    OldAlpha,
+    //以后新增的类型, 不能因此读取失败
+    #[serde(other)]
+    Other,
+}
+
+impl VersionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VersionType::Release => "release",
+            VersionType::Snapshot => "snapshot",
+            VersionType::OldBeta => "old_beta",
+            VersionType::OldAlpha => "old_alpha",
+            VersionType::Other => "other",
+        }
+    }
+}
+
+impl VersionManifest {
+    pub fn get(&self, id: &str) -> Option<&ManifestVersion> {
+        self.versions.iter().find(|v| v.id == id)
+    }
+
+    //types 为空时返回所有版本, 保持清单中的顺序(从新到旧)
+    pub fn filter(&self, types: &[VersionType]) -> Vec<&ManifestVersion> {
+        self.versions
+            .iter()
+            .filter(|v| types.is_empty() || types.contains(&v.version_type))
+            .collect()
+    }
+
+    pub fn release_times(&self) -> ReleaseTimes {
+        self.versions
+            .iter()
+            .map(|v| (v.id.clone(), v.release_time))
+            .collect()
+    }
+}
+
+//清单来自哪里, 前端据此提示是否离线
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ManifestOrigin {
+    //重新下载
+    Network,
+    //服务器返回 304, 使用缓存
+    NotModified,
+    //请求失败, 使用缓存
+    Offline,
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchedManifest {
+    pub manifest: VersionManifest,
+    pub origin: ManifestOrigin,
+}
+
+impl FetchedManifest {
+    pub fn list(&self, types: &[VersionType]) -> VersionList {
+        VersionList {
+            latest: self.manifest.latest.clone(),
+            versions: self.manifest.filter(types).into_iter().cloned().collect(),
+            origin: self.origin,
+        }
+    }
+}
+
+//返回给前端的版本列表
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionList {
+    pub latest: LatestVersions,
+    pub versions: Vec<ManifestVersion>,
+    pub origin: ManifestOrigin,
+}
+
+//缓存对应的地址和校验信息, 地址改变后不再用来重新验证
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheMeta {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+pub struct ManifestClient {
+    base_url: String,
+    cache_dir: PathBuf,
+    client: Client,
+}
+
+impl ManifestClient {
+    pub fn new(base_url: &str, cache_dir: PathBuf) -> Result<Self> {
+        Ok(ManifestClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            cache_dir,
+            client: download::client()?,
+        })
+    }
+
+    pub fn manifest_url(&self) -> String {
+        format!("{}{}", self.base_url, MANIFEST_PATH)
+    }
+
+    //有缓存时带上 ETag/Last-Modified 重新验证, 请求失败时使用缓存
+    pub fn fetch(&self) -> Result<FetchedManifest> {
+        let url = self.manifest_url();
+        let cached = self.read_cache();
+        let meta = match &cached {
+            Some((_, meta)) if meta.url == url => Some(meta),
+            _ => None,
+        };
+        match self.request(&url, meta) {
+            Ok(Some((manifest, meta))) => {
+                //缓存只在下次离线或重新验证时使用, 写入失败不影响这次下载的结果
+                let _ = self.write_cache(&manifest, &meta);
+                Ok(FetchedManifest {
+                    manifest,
+                    origin: ManifestOrigin::Network,
+                })
+            }
+            Ok(None) => match cached {
+                Some((manifest, _)) => Ok(FetchedManifest {
+                    manifest,
+                    origin: ManifestOrigin::NotModified,
+                }),
+                None => bail!("Server returned 304 for `{}` without a cached copy", url),
+            },
+            Err(e) => match cached {
+                Some((manifest, _)) => Ok(FetchedManifest {
+                    manifest,
+                    origin: ManifestOrigin::Offline,
+                }),
+                None => Err(e),
+            },
+        }
+    }
+
+    //没有修改时返回 None
+    fn request(
+        &self,
+        url: &str,
+        meta: Option<&CacheMeta>,
+    ) -> Result<Option<(VersionManifest, CacheMeta)>> {
+        let mut request = self.client.get(url);
+        if let Some(meta) = meta {
+            if let Some(etag) = &meta.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &meta.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = request.send()?.error_for_status()?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let meta = CacheMeta {
+            url: url.to_string(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+        let bytes = response.bytes()?;
+        let manifest = serde_json::from_slice(&bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to parse json from `{}`: {}", url, e))?;
+        Ok(Some((manifest, meta)))
+    }
+
+    //缓存损坏时当作没有缓存
+    fn read_cache(&self) -> Option<(VersionManifest, CacheMeta)> {
+        let manifest = fs::read(self.cache_dir.join(CACHE_FILE)).ok()?;
+        let manifest = serde_json::from_slice(&manifest).ok()?;
+        let meta = fs::read(self.cache_dir.join(CACHE_META_FILE))
+            .ok()
+            .and_then(|meta| serde_json::from_slice(&meta).ok())
+            .unwrap_or_default();
+        Some((manifest, meta))
+    }
+
+    //先写清单再写校验信息, 中途失败时校验信息对应旧的清单也只是多下载一次
+    fn write_cache(&self, manifest: &VersionManifest, meta: &CacheMeta) -> Result<()> {
+        write_atomic(
+            &self.cache_dir.join(CACHE_FILE),
+            &serde_json::to_vec(manifest)?,
+        )?;
+        write_atomic(
+            &self.cache_dir.join(CACHE_META_FILE),
+            &serde_json::to_vec_pretty(meta)?,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_server::TestServer;
+
+    const FIXTURE: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/version_manifest_v2.json"
+    ));
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest: VersionManifest = serde_json::from_slice(FIXTURE).unwrap();
+        assert_eq!(manifest.latest.release, "1.21.4");
+        assert_eq!(manifest.versions.len(), 6);
+        let version = manifest.get("1.21.4").unwrap();
+        assert_eq!(version.version_type, VersionType::Release);
+        assert_eq!(version.sha1, "a3bcba436caa849622fd7e1e5b89489ed6c9ac63");
+        assert_eq!(
+            version.release_time.to_rfc3339(),
+            "2024-12-03T10:12:57+00:00"
+        );
+
+        let ids = |types: &[VersionType]| -> Vec<String> {
+            manifest
+                .filter(types)
+                .into_iter()
+                .map(|v| v.id.clone())
+                .collect()
+        };
+        assert_eq!(ids(&[VersionType::Release]), vec!["1.21.4", "1.12.2"]);
+        assert_eq!(
+            ids(&[VersionType::OldBeta, VersionType::OldAlpha]),
+            vec!["b1.7.3", "a1.2.6"]
+        );
+        assert_eq!(ids(&[]).len(), 6);
+        assert!(manifest.release_times().contains_key("25w02a"));
+
+        let unknown: ManifestVersion = serde_json::from_value(serde_json::json!({
+            "id": "x", "type": "experiment", "url": "", "sha1": "",
+            "time": "2025-01-01T00:00:00+00:00", "releaseTime": "2025-01-01T00:00:00+00:00",
+        }))
+        .unwrap();
+        assert_eq!(unknown.version_type, VersionType::Other);
+    }
+
+    #[test]
+    fn test_fetch_with_cache() {
+        let server = TestServer::start();
+        server.set_with_headers(
+            MANIFEST_PATH,
+            FIXTURE.to_vec(),
+            &[
+                ("ETag", "\"v1\""),
+                ("Last-Modified", "Wed, 08 Jan 2025 13:34:35 GMT"),
+            ],
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let client = ManifestClient::new(&server.url("/"), dir.path().to_path_buf()).unwrap();
+
+        let fetched = client.fetch().unwrap();
+        assert_eq!(fetched.origin, ManifestOrigin::Network);
+        assert_eq!(fetched.manifest.versions.len(), 6);
+        assert!(dir.path().join(CACHE_FILE).exists());
+        let list = fetched.list(&[VersionType::Snapshot]);
+        assert_eq!(list.versions.len(), 2);
+        assert_eq!(serde_json::to_value(&list).unwrap()["origin"], "network");
+
+        //第二次请求带上缓存的校验信息
+        let fetched = client.fetch().unwrap();
+        assert_eq!(fetched.origin, ManifestOrigin::NotModified);
+        assert_eq!(fetched.manifest.latest.snapshot, "25w02a");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(!requests[0].headers.contains_key("if-none-match"));
+        assert_eq!(requests[1].headers["if-none-match"], "\"v1\"");
+        assert_eq!(
+            requests[1].headers["if-modified-since"],
+            "Wed, 08 Jan 2025 13:34:35 GMT"
+        );
+
+        //清单更新
+        let mut manifest: VersionManifest = serde_json::from_slice(FIXTURE).unwrap();
+        manifest.versions.truncate(2);
+        server.set_with_headers(
+            MANIFEST_PATH,
+            serde_json::to_vec(&manifest).unwrap(),
+            &[("ETag", "\"v2\"")],
+        );
+        let fetched = client.fetch().unwrap();
+        assert_eq!(fetched.origin, ManifestOrigin::Network);
+        assert_eq!(fetched.manifest.versions.len(), 2);
+
+        //服务器出错时使用缓存
+        server.remove(MANIFEST_PATH);
+        let fetched = client.fetch().unwrap();
+        assert_eq!(fetched.origin, ManifestOrigin::Offline);
+        assert_eq!(fetched.manifest.versions.len(), 2);
+
+        //地址改变后不带校验信息, 也没有缓存可以回退时返回错误
+        let other = ManifestClient::new(&server.url("/mirror"), dir.path().to_path_buf()).unwrap();
+        assert_eq!(other.fetch().unwrap().origin, ManifestOrigin::Offline);
+        assert!(!server.requests()[4].headers.contains_key("if-none-match"));
+        let empty = tempfile::tempdir().unwrap();
+        let client = ManifestClient::new(&server.url("/"), empty.path().to_path_buf()).unwrap();
+        assert!(client.fetch().is_err());
+    }
+
+    #[test]
+    fn test_corrupt_cache() {
+        let server = TestServer::start();
+        server.set_with_headers(MANIFEST_PATH, FIXTURE.to_vec(), &[("ETag", "\"v1\"")]);
+        let dir = tempfile::tempdir().unwrap();
+        let client = ManifestClient::new(&server.url(""), dir.path().to_path_buf()).unwrap();
+        client.fetch().unwrap();
+
+        //缓存损坏时不发送校验信息, 重新下载
+        fs::write(dir.path().join(CACHE_FILE), "{").unwrap();
+        assert_eq!(client.fetch().unwrap().origin, ManifestOrigin::Network);
+        assert!(!server.requests()[1].headers.contains_key("if-none-match"));
+    }
+
+    #[test]
+    fn test_cache_write_failure() {
+        let server = TestServer::start();
+        server.set(MANIFEST_PATH, FIXTURE.to_vec());
+        let dir = tempfile::tempdir().unwrap();
+        //缓存目录的位置是一个文件, 无法写入缓存
+        let cache_dir = dir.path().join("cache");
+        fs::write(&cache_dir, "").unwrap();
+        let client = ManifestClient::new(&server.url(""), cache_dir).unwrap();
+
+        let fetched = client.fetch().unwrap();
+        assert_eq!(fetched.origin, ManifestOrigin::Network);
+        assert_eq!(fetched.manifest.versions.len(), 6);
+    }
+}
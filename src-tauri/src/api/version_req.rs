@@ -0,0 +1,406 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use anyhow::{bail, Result};
+
+use super::version::Version;
+
+//版本范围, 支持三种写法:
+//比较符: >=1.20 <1.21, =1.20.1, 多组之间用 || 连接
+//Fabric/npm: ~1.20, ^1.20.1, 1.20.x, 1.2 - 1.4
+//Maven/Forge: [1.19,1.20), (,1.20], [1.0,1.2),[1.5,)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReq {
+    source: String,
+    //满足其中任意一组即可, 每组内的条件都要满足
+    alternatives: Vec<Vec<Comparator>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    version: Version,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl VersionReq {
+    pub fn parse(s: &str) -> Result<Self> {
+        let source = s.trim();
+        let alternatives = if source.starts_with(['[', '(']) {
+            parse_maven(source)?
+        } else {
+            source
+                .split("||")
+                .map(parse_group)
+                .collect::<Result<Vec<_>>>()?
+        };
+        Ok(VersionReq {
+            source: source.to_string(),
+            alternatives,
+        })
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        let version = version.normalized();
+        self.alternatives
+            .iter()
+            .any(|group| group.iter().all(|c| c.matches(&version)))
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        VersionReq::parse(s)
+    }
+}
+
+impl std::fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Comparator {
+    fn new(op: Op, version: Version) -> Self {
+        Comparator { op, version }
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        let ordering = version.cmp(&self.version);
+        match self.op {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+        }
+    }
+}
+
+//可能不完整的版本号, 如 1.20 或 1.20.x, 也可以超过三位, 如 14.23.5.2860
+struct Partial {
+    parts: Vec<u32>,
+    wildcard: bool,
+    pre_release: Option<String>,
+}
+
+impl Partial {
+    fn parse(s: &str) -> Result<Self> {
+        let s = s.trim().trim_start_matches(['v', 'V']);
+        let s = s.split('+').next().unwrap_or(s);
+        let (numbers, pre_release) = match s.split_once('-') {
+            Some((numbers, pre_release)) => (numbers, Some(pre_release.to_string())),
+            None => (s, None),
+        };
+        let mut parts = Vec::new();
+        let mut wildcard = false;
+        for part in numbers.split('.') {
+            if matches!(part, "x" | "X" | "*") {
+                wildcard = true;
+                break;
+            }
+            parts.push(
+                part.parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid version `{}`", s))?,
+            );
+        }
+        Ok(Partial {
+            parts,
+            wildcard,
+            pre_release,
+        })
+    }
+
+    fn part(&self, index: usize) -> u32 {
+        self.parts.get(index).copied().unwrap_or(0)
+    }
+
+    fn is_partial(&self) -> bool {
+        self.wildcard || self.parts.len() < 3
+    }
+
+    fn lower(&self) -> Version {
+        Version::new(self.part(0), Some(self.part(1)), Some(self.part(2)))
+            .with_extra(self.parts.get(3..).unwrap_or_default().to_vec())
+            .with_pre_release(self.pre_release.clone())
+    }
+
+    //1.20.x => 1.21.0-0, 没有数字时不设上限
+    fn upper(&self) -> Option<Version> {
+        let len = self.parts.len();
+        if len == 0 {
+            return None;
+        }
+        Some(bump(self, len - 1))
+    }
+
+    //没有运算符时: 1.20.x 是范围, 1.20 与 Fabric 一样表示等于 1.20.0
+    fn range(&self) -> Vec<Comparator> {
+        match self.upper() {
+            Some(upper) if self.wildcard => vec![
+                Comparator::new(Op::Ge, self.lower()),
+                Comparator::new(Op::Lt, upper),
+            ],
+            Some(_) => vec![Comparator::new(Op::Eq, self.lower())],
+            None => Vec::new(),
+        }
+    }
+}
+
+//把第 index 位加一, 后面补 0, 预发布版本 0 比所有该版本的预发布版本都小
+fn bump(partial: &Partial, index: usize) -> Version {
+    let part = |i: usize| match i.cmp(&index) {
+        Ordering::Less => partial.part(i),
+        Ordering::Equal => partial.part(i) + 1,
+        Ordering::Greater => 0,
+    };
+    let extra = (3..=index).map(part).collect();
+    Version::new(part(0), Some(part(1)), Some(part(2)))
+        .with_extra(extra)
+        .with_pre_release(Some("0".to_string()))
+}
+
+fn parse_group(group: &str) -> Result<Vec<Comparator>> {
+    //把 ">= 1.20" 这种运算符和版本分开写的合并起来
+    let mut tokens: Vec<String> = Vec::new();
+    let mut pending = String::new();
+    for token in group.split_whitespace() {
+        if token.chars().all(|ch| "<>=~^".contains(ch)) {
+            pending.push_str(token);
+        } else {
+            tokens.push(format!("{}{}", pending, token));
+            pending.clear();
+        }
+    }
+    if !pending.is_empty() {
+        bail!("Missing version after `{}`", pending);
+    }
+
+    let mut comparators = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        //1.2 - 1.4
+        if tokens.get(i + 1).map(|t| t.as_str()) == Some("-") {
+            let Some(to) = tokens.get(i + 2) else {
+                bail!("Missing upper bound in `{}`", group);
+            };
+            let from = Partial::parse(&tokens[i])?;
+            let to = Partial::parse(to)?;
+            comparators.push(Comparator::new(Op::Ge, from.lower()));
+            match to.upper() {
+                Some(upper) if to.is_partial() => comparators.push(Comparator::new(Op::Lt, upper)),
+                Some(_) => comparators.push(Comparator::new(Op::Le, to.lower())),
+                None => {}
+            }
+            i += 3;
+            continue;
+        }
+        comparators.extend(parse_comparator(&tokens[i])?);
+        i += 1;
+    }
+    Ok(comparators)
+}
+
+fn parse_comparator(token: &str) -> Result<Vec<Comparator>> {
+    let split = token
+        .find(|ch: char| !"<>=~^".contains(ch))
+        .unwrap_or(token.len());
+    let (op, version) = token.split_at(split);
+    let partial = Partial::parse(version)?;
+    let lower = partial.lower();
+    let comparators = match op {
+        "" | "=" | "==" => partial.range(),
+        ">=" => vec![Comparator::new(Op::Ge, lower)],
+        "<" => vec![Comparator::new(Op::Lt, lower)],
+        ">" => match partial.upper() {
+            Some(upper) if partial.wildcard => vec![Comparator::new(Op::Ge, upper)],
+            _ => vec![Comparator::new(Op::Gt, lower)],
+        },
+        "<=" => match partial.upper() {
+            Some(upper) if partial.wildcard => vec![Comparator::new(Op::Lt, upper)],
+            _ => vec![Comparator::new(Op::Le, lower)],
+        },
+        //~1.20.1 => >=1.20.1 <1.21.0, ~1 => >=1.0.0 <2.0.0
+        "~" | "~>" => {
+            let index = if partial.parts.len() >= 2 { 1 } else { 0 };
+            tilde_or_caret(&partial, index)
+        }
+        //^1.20.1 => <2.0.0, ^0.14.2 => <0.15.0, ^0.0.3 => <0.0.4
+        "^" => {
+            let index = (0..partial.parts.len().min(3))
+                .find(|&i| partial.part(i) != 0 || i + 1 == partial.parts.len())
+                .unwrap_or(0);
+            tilde_or_caret(&partial, index)
+        }
+        _ => bail!("Unknown operator `{}` in `{}`", op, token),
+    };
+    Ok(comparators)
+}
+
+fn tilde_or_caret(partial: &Partial, index: usize) -> Vec<Comparator> {
+    if partial.parts.is_empty() {
+        return Vec::new();
+    }
+    vec![
+        Comparator::new(Op::Ge, partial.lower()),
+        Comparator::new(Op::Lt, bump(partial, index)),
+    ]
+}
+
+fn parse_maven(source: &str) -> Result<Vec<Vec<Comparator>>> {
+    let mut alternatives = Vec::new();
+    let mut rest = source;
+    loop {
+        rest = rest.trim_start();
+        let lower_inclusive = match rest.chars().next() {
+            Some('[') => true,
+            Some('(') => false,
+            _ => bail!("Invalid maven range `{}`", source),
+        };
+        let end = rest
+            .find([']', ')'])
+            .ok_or_else(|| anyhow::anyhow!("Unclosed maven range `{}`", source))?;
+        let upper_inclusive = &rest[end..end + 1] == "]";
+        let body = &rest[1..end];
+
+        let mut group = Vec::new();
+        match body.split_once(',') {
+            //[1.20] 表示只能是 1.20
+            None => {
+                if !(lower_inclusive && upper_inclusive) {
+                    bail!("Invalid maven range `{}`", source);
+                }
+                group.push(Comparator::new(Op::Eq, Partial::parse(body)?.lower()));
+            }
+            Some((lower, upper)) => {
+                if !lower.trim().is_empty() {
+                    let op = if lower_inclusive { Op::Ge } else { Op::Gt };
+                    group.push(Comparator::new(op, Partial::parse(lower)?.lower()));
+                }
+                if !upper.trim().is_empty() {
+                    let op = if upper_inclusive { Op::Le } else { Op::Lt };
+                    group.push(Comparator::new(op, Partial::parse(upper)?.lower()));
+                }
+            }
+        }
+        alternatives.push(group);
+
+        rest = rest[end + 1..].trim_start();
+        match rest.strip_prefix(',') {
+            Some(next) => rest = next,
+            None if rest.is_empty() => break,
+            None => bail!("Invalid maven range `{}`", source),
+        }
+    }
+    Ok(alternatives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(req: &str, version: &str) -> bool {
+        VersionReq::parse(req)
+            .unwrap()
+            .matches(&Version::from_string(&version.to_string(), None).unwrap())
+    }
+
+    #[test]
+    fn test_comparators() {
+        assert!(matches(">=1.20 <1.21", "1.20"));
+        assert!(matches(">=1.20 <1.21", "1.20.4"));
+        assert!(!matches(">=1.20 <1.21", "1.21"));
+        assert!(!matches(">=1.20 <1.21", "1.19.4"));
+        assert!(matches(">= 1.20", "1.20.1"));
+        assert!(matches("=1.20.1", "1.20.1"));
+        assert!(!matches("=1.20.1", "1.20.2"));
+        assert!(matches("1.20", "1.20.0"));
+        assert!(!matches("1.20", "1.20.1"));
+        assert!(matches(">1.20", "1.20.1"));
+        assert!(!matches(">1.20", "1.20"));
+        assert!(matches("<=1.20.1", "1.20.1"));
+        assert!(matches("<1.19 || >=1.20", "1.18.2"));
+        assert!(matches("<1.19 || >=1.20", "1.20.6"));
+        assert!(!matches("<1.19 || >=1.20", "1.19.2"));
+        assert!(matches("*", "1.0"));
+        assert!(matches("", "1.0"));
+    }
+
+    #[test]
+    fn test_fabric() {
+        assert!(matches("~1.20", "1.20.6"));
+        assert!(!matches("~1.20", "1.21"));
+        assert!(matches("~1.20.1", "1.20.4"));
+        assert!(!matches("~1.20.1", "1.20.0"));
+        assert!(matches("^1.20.1", "1.99"));
+        assert!(!matches("^1.20.1", "2.0.0"));
+        assert!(matches("^0.14.2", "0.14.9"));
+        assert!(!matches("^0.14.2", "0.15.0"));
+        assert!(matches("1.20.x", "1.20.4"));
+        assert!(!matches("1.20.x", "1.21"));
+        assert!(matches("1.x", "1.21.1"));
+        assert!(!matches(">1.20.x", "1.20.4"));
+        assert!(matches(">1.20.x", "1.21"));
+        assert!(matches("<=1.20.x", "1.20.6"));
+        assert!(matches("1.2 - 1.4", "1.4.2"));
+        assert!(!matches("1.2 - 1.4", "1.5"));
+        assert!(matches("1.2.0 - 1.4.0", "1.4.0"));
+        assert!(!matches("1.2.0 - 1.4.0", "1.4.1"));
+        //上限不包含该版本的预发布版本
+        assert!(!matches("~1.20", "1.21-rc1"));
+        assert!(matches(">=1.21-pre1", "1.21-rc1"));
+    }
+
+    #[test]
+    fn test_maven() {
+        assert!(matches("[1.19,1.20)", "1.19.4"));
+        assert!(!matches("[1.19,1.20)", "1.20"));
+        assert!(matches("[1.19,1.20]", "1.20"));
+        assert!(!matches("(1.19,1.20]", "1.19"));
+        assert!(matches("(,1.20]", "1.12.2"));
+        assert!(matches("[1.20,)", "1.21"));
+        assert!(matches("[1.20.1]", "1.20.1"));
+        assert!(!matches("[1.20.1]", "1.20.2"));
+        assert!(matches("[1.0,1.2),[1.5,)", "1.6"));
+        assert!(!matches("[1.0,1.2),[1.5,)", "1.3"));
+        assert!(matches("[47.1.0,)", "47.2.0"));
+        //Forge 和 Java 的版本号可以超过三位
+        assert!(matches("[14.23.5.2860,)", "14.23.5.2860"));
+        assert!(matches("[14.23.5.2847,14.23.5.2860]", "14.23.5.2855"));
+        assert!(!matches("[14.23.5.2847,14.23.5.2860]", "14.23.5.2861"));
+        assert!(matches(">=11.0.2.1", "11.0.3"));
+        assert!(!matches(">=11.0.2.1", "11.0.2"));
+        assert!(matches("11.0.2.1", "11.0.2.1"));
+        assert!(matches("11.0.2.x", "11.0.2.7"));
+        assert!(!matches("11.0.2.x", "11.0.3"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        for req in [
+            ">=",
+            "1.2.3.a",
+            "abc",
+            "[1.19,1.20",
+            "(1.20)",
+            "[1.19,1.20) x",
+            "!1.20",
+            "1.2 -",
+        ] {
+            assert!(VersionReq::parse(req).is_err(), "{}", req);
+        }
+        assert_eq!(
+            ">=1.20 <1.21".parse::<VersionReq>().unwrap().to_string(),
+            ">=1.20 <1.21"
+        );
+    }
+}
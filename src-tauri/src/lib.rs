@@ -1,9 +1,33 @@
-use std::path::PathBuf;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+};
 
 use rfd::FileDialog;
 use serde_json::Value;
+use tauri::Emitter;
 
-use settings::setting::Setting;
+use api::{
+    dirs,
+    download_manager::{
+        CancelToken, DownloadEvent, DownloadManager, DownloadProgress, DownloadTask,
+        DOWNLOAD_PROGRESS_EVENT,
+    },
+    java_runtime::{RuntimeInstaller, RuntimeSource},
+    launch::LaunchPlan,
+    version_manifest::{ManifestClient, VersionList, VersionType, MOJANG_META_BASE},
+};
+use settings::{
+    error::SettingError,
+    events::SETTING_CHANGED_EVENT,
+    java_versions::JavaVersions,
+    profile::{ImportMode, ProfileChange},
+    recovery::StartupProblem,
+    setting::{InstanceInfo, Setting, SettingValue},
+    setting_manager::Settings,
+    watcher::SettingWatcher,
+};
 
 mod api;
 mod settings;
@@ -24,45 +48,284 @@ fn file_dialog(filters: Vec<(String, Vec<String>)>, set_directory: String) -> Op
 ///------------------------
 /// id: -1 is globle
 #[tauri::command]
-fn get_setting_value(id: i32, item_name: String) -> Result<Value, String> {
-    let setting = Setting::instance();
-    let setting = setting.read().unwrap();
-
-    let globle_setting = setting.get_globle().get_setting();
-    if id == -1 {
-        if let Ok(value) = globle_setting.get(item_name) {
-            return Ok(value.clone());
+fn get_setting_value(id: i32, item_name: String) -> Result<SettingValue, SettingError> {
+    Setting::instance()?
+        .read()
+        .unwrap()
+        .get_value(id, item_name)
+}
+
+//取消整合包的覆盖, item_name 为空时取消所有覆盖
+#[tauri::command]
+fn reset_setting_value(id: i32, item_name: Option<String>) -> Result<(), SettingError> {
+    Setting::instance()?
+        .write()
+        .unwrap()
+        .reset_override(id, item_name)
+}
+
+//所有设置项的描述, 设置页面据此生成
+#[tauri::command]
+fn get_setting_schema() -> Result<Value, String> {
+    Settings::describe().map_err(|e| e.to_string())
+}
+
+//返回修改后的实际值, 旧的字符串数组参数由各设置项自己兼容
+#[tauri::command]
+fn change_setting_value(id: i32, item_name: String, value: Value) -> Result<Value, SettingError> {
+    Setting::instance()?
+        .write()
+        .unwrap()
+        .change(id, item_name, value)
+}
+
+//下载对应大版本的 java 并加入全局 java 列表
+//下载和解压很慢, 在单独的线程中执行, 不阻塞界面
+#[tauri::command(async)]
+fn install_java_runtime(major: u32, source: Option<RuntimeSource>) -> Result<Value, String> {
+    let runtime_dir = dirs::get_runtime_dirs().map_err(|e| e.to_string())?;
+    let installer = RuntimeInstaller::new(source.unwrap_or_default(), runtime_dir)
+        .map_err(|e| e.to_string())?;
+    let java = installer
+        .install(major)
+        .map_err(|e| e.to_string())?
+        .to_string_lossy()
+        .to_string();
+
+    let mut setting = Setting::instance()
+        .map_err(|e| e.to_string())?
+        .write()
+        .unwrap();
+    let java_setting = setting
+        .get_globle()
+        .get_setting()
+        .get("java".to_string())
+        .map_err(|e| e.to_string())?;
+    //重新安装时路径不变, 已经在列表里了
+    let registered = java_setting["versions"]
+        .as_array()
+        .is_some_and(|versions| versions.iter().any(|v| v["path"] == java.as_str()));
+    if !registered {
+        setting
+            .change(
+                -1,
+                "java".to_string(),
+                serde_json::json!({ "command": "add", "path": java }),
+            )
+            .map_err(|e| e.to_string())?;
+    }
+    setting
+        .get_globle()
+        .get_setting()
+        .get("java".to_string())
+        .map_err(|e| e.to_string())
+}
+
+//启动时自动修复的问题, 前端用来提示用户
+//启动失败时只有一个 fatal 的问题, 其它设置命令都会返回 unavailable
+#[tauri::command]
+fn get_startup_problems() -> Vec<StartupProblem> {
+    Setting::startup_problems()
+}
+
+///instance
+///------------------------
+#[tauri::command]
+fn list_instances() -> Result<Vec<InstanceInfo>, SettingError> {
+    Ok(Setting::instance()?.read().unwrap().list_instances())
+}
+
+#[tauri::command]
+fn create_instance(name: String, path: PathBuf) -> Result<InstanceInfo, SettingError> {
+    Setting::instance()?
+        .write()
+        .unwrap()
+        .create_instance(name, path)
+}
+
+#[tauri::command]
+fn rename_instance(id: i32, name: String) -> Result<InstanceInfo, SettingError> {
+    Setting::instance()?
+        .write()
+        .unwrap()
+        .rename_instance(id, name)
+}
+
+#[tauri::command]
+fn relocate_instance(id: i32, path: PathBuf) -> Result<InstanceInfo, SettingError> {
+    Setting::instance()?
+        .write()
+        .unwrap()
+        .relocate_instance(id, path)
+}
+
+#[tauri::command]
+fn remove_instance(id: i32, delete_files: bool) -> Result<(), SettingError> {
+    Setting::instance()?
+        .write()
+        .unwrap()
+        .remove_instance(id, delete_files)
+}
+
+//游戏版本列表, types 为空时返回所有类型, 离线时使用缓存的清单
+//需要请求网络, 在单独的线程中执行
+#[tauri::command(async)]
+fn list_minecraft_versions(
+    types: Option<Vec<VersionType>>,
+    base_url: Option<String>,
+) -> Result<VersionList, String> {
+    let cache_dir = dirs::get_cache_dirs().map_err(|e| e.to_string())?;
+    let client = ManifestClient::new(base_url.as_deref().unwrap_or(MOJANG_META_BASE), cache_dir)
+        .map_err(|e| e.to_string())?;
+    let fetched = client.fetch().map_err(|e| e.to_string())?;
+    Ok(fetched.list(&types.unwrap_or_default()))
+}
+
+//准备启动需要的 java, classpath 和参数, id 为 -1 时使用全局的 java 设置
+#[tauri::command(async)]
+fn prepare_launch(id: i32, game_dir: PathBuf, version_id: String) -> Result<LaunchPlan, String> {
+    let java = Setting::instance()
+        .map_err(|e| e.to_string())?
+        .read()
+        .unwrap()
+        .get_value(id, "java".to_string())
+        .map_err(|e| e.to_string())?
+        .value;
+    let java_versions: JavaVersions = serde_json::from_value(java).map_err(|e| e.to_string())?;
+    LaunchPlan::for_version(&game_dir, &version_id, &java_versions).map_err(|e| e.to_string())
+}
+
+//正在进行的下载, 用于取消
+fn downloads() -> &'static Mutex<HashMap<String, CancelToken>> {
+    static DOWNLOADS: OnceLock<Mutex<HashMap<String, CancelToken>>> = OnceLock::new();
+    DOWNLOADS.get_or_init(Default::default)
+}
+
+//job 由前端指定, 进度事件和取消下载都用它区分, 同一个 job 正在下载时返回错误
+//在单独的线程中执行, 下载时还可以调用 cancel_download
+#[tauri::command(async)]
+fn download_files(
+    app: tauri::AppHandle,
+    job: String,
+    tasks: Vec<DownloadTask>,
+) -> Result<DownloadProgress, String> {
+    let cancel = CancelToken::new();
+    match downloads().lock().unwrap().entry(job.clone()) {
+        Entry::Occupied(_) => return Err(format!("Download job `{}` is already running", job)),
+        Entry::Vacant(entry) => {
+            entry.insert(cancel.clone());
         }
-        return Err("err get globle setting value".to_string());
-    } else {
-        if let Some(modpack_setting_manager) = setting.get(id) {
-            if let Ok(value) = modpack_setting_manager
-                .get_setting()
-                .get(item_name, &globle_setting)
-            {
-                return Ok(value.clone());
-            }
+    }
+    let result = DownloadManager::new().and_then(|manager| {
+        manager.run(tasks, &cancel, |progress| {
+            let event = DownloadEvent {
+                job: job.clone(),
+                progress: progress.clone(),
+            };
+            let _ = app.emit(DOWNLOAD_PROGRESS_EVENT, event);
+        })
+    });
+    downloads().lock().unwrap().remove(&job);
+    result.map_err(|e| e.to_string())
+}
+
+//返回是否有这个下载
+#[tauri::command]
+fn cancel_download(job: String) -> bool {
+    match downloads().lock().unwrap().get(&job) {
+        Some(cancel) => {
+            cancel.cancel();
+            true
         }
+        None => false,
     }
-    Err("err get setting value".to_string())
 }
 
+//导出全局设置(id 为 -1)或整合包覆盖的设置
 #[tauri::command]
-fn change_setting_value(id: i32, item_name: String, value: Vec<String>) {
-    let rusult = Setting::instance()
+fn export_settings_profile(id: i32, path: PathBuf) -> Result<(), SettingError> {
+    Setting::instance()?
+        .read()
+        .unwrap()
+        .export_profile(id, &path)
+}
+
+//导入前先预览会改变的设置项
+#[tauri::command]
+fn preview_settings_profile(
+    id: i32,
+    path: PathBuf,
+    mode: ImportMode,
+) -> Result<Vec<ProfileChange>, SettingError> {
+    Setting::instance()?
+        .read()
+        .unwrap()
+        .preview_import(id, &path, mode)
+}
+
+#[tauri::command]
+fn import_settings_profile(
+    id: i32,
+    path: PathBuf,
+    mode: ImportMode,
+) -> Result<Vec<ProfileChange>, SettingError> {
+    Setting::instance()?
         .write()
         .unwrap()
-        .change(id, item_name, value);
+        .import_profile(id, &path, mode)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            //设置无法加载时仍然启动, 界面通过 get_startup_problems 显示错误
+            let Ok(setting) = Setting::init() else {
+                return Ok(());
+            };
+            let handle = app.handle().clone();
+            let mut setting = setting.write().unwrap();
+            setting.set_listener(Box::new(move |event| {
+                let _ = handle.emit(SETTING_CHANGED_EVENT, event);
+            }));
+            //外部修改设置文件后重新读取, 回调在监听线程中执行
+            //监听失败只是无法自动刷新, 记录下来继续启动
+            let watched = SettingWatcher::start(|paths| {
+                if let Ok(setting) = Setting::instance() {
+                    setting.write().unwrap().reload_files(&paths);
+                }
+            })
+            .and_then(|watcher| setting.set_watcher(watcher));
+            if let Err(err) = watched {
+                let setting_file = setting.get_globle().get_setting_file_path();
+                setting.add_problem(StartupProblem::new(
+                    setting_file.parent().unwrap_or(&setting_file),
+                    format!("Failed to watch setting files: {}", err),
+                ));
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             file_dialog,
             get_setting_value,
-            change_setting_value
+            change_setting_value,
+            reset_setting_value,
+            get_setting_schema,
+            get_startup_problems,
+            install_java_runtime,
+            list_instances,
+            create_instance,
+            rename_instance,
+            relocate_instance,
+            remove_instance,
+            export_settings_profile,
+            preview_settings_profile,
+            import_settings_profile,
+            list_minecraft_versions,
+            download_files,
+            cancel_download,
+            prepare_launch
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,121 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+//先写入临时文件并 fsync, 再重命名覆盖目标文件
+//覆盖前把旧文件保留为 `<name>.bak`, 只保留一份
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    write_atomic_with(path, |file| file.write_all(content))
+}
+
+pub fn write_atomic_with(
+    path: &Path,
+    write: impl FnOnce(&mut File) -> io::Result<()>,
+) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(parent)?;
+
+    let temp_path = sibling(path, "tmp");
+    let result = File::create(&temp_path).and_then(|mut file| {
+        write(&mut file)?;
+        file.sync_all()
+    });
+    if let Err(err) = result {
+        //写入失败时原文件不受影响
+        let _ = fs::remove_file(&temp_path);
+        return Err(err);
+    }
+
+    if path.is_file() {
+        backup(path)?;
+    }
+    fs::rename(&temp_path, path)?;
+    sync_dir(parent);
+    Ok(())
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    sibling(path, "bak")
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(extension);
+    path.with_file_name(file_name)
+}
+
+//优先使用硬链接, 目标文件在任何时刻都存在
+fn backup(path: &Path) -> io::Result<()> {
+    let backup_path = backup_path(path);
+    if backup_path.exists() {
+        fs::remove_file(&backup_path)?;
+    }
+    if fs::hard_link(path, &backup_path).is_err() {
+        fs::copy(path, &backup_path)?;
+    }
+    Ok(())
+}
+
+//重命名只有在目录也落盘后才算完成, windows 不支持打开目录
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("setting.json");
+
+        write_atomic(&path, b"first").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+        assert!(!backup_path(&path).exists());
+
+        write_atomic(&path, b"second").unwrap();
+        write_atomic(&path, b"third").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third");
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "second");
+        assert!(!sibling(&path, "tmp").exists());
+    }
+
+    #[test]
+    fn test_partial_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("setting.json");
+        write_atomic(&path, b"{\"java\": {}}").unwrap();
+
+        //模拟写到一半磁盘满了
+        let err = write_atomic_with(&path, |file| {
+            file.write_all(b"{\"ja")?;
+            Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::StorageFull);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"java\": {}}");
+        assert!(!sibling(&path, "tmp").exists());
+        assert!(!backup_path(&path).exists());
+
+        //上次崩溃留下的临时文件会被覆盖
+        fs::write(sibling(&path, "tmp"), "garbage from a crash").unwrap();
+        write_atomic(&path, b"{}").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+        assert_eq!(
+            fs::read_to_string(backup_path(&path)).unwrap(),
+            "{\"java\": {}}"
+        );
+    }
+}
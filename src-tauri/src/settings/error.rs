@@ -0,0 +1,47 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+//返回给前端的设置错误, kind 用来区分显示方式
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SettingError {
+    InstanceNotFound { id: i32 },
+    ItemNotFound { item: String },
+    Readonly { item: String },
+    //值的类型或内容不对, 设置没有被修改
+    InvalidValue { item: String, message: String },
+    //导入的配置文件无法读取或内容不对, 设置没有被修改
+    InvalidProfile { message: String },
+    //整合包的名字或文件夹不对, 登记没有被修改
+    InvalidInstance { message: String },
+    //修改成功但保存失败
+    Io { message: String },
+    //启动时设置加载失败, 只能查看启动问题
+    Unavailable { message: String },
+}
+
+impl Display for SettingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingError::InstanceNotFound { id } => write!(f, "Instance not found for id: {}", id),
+            SettingError::ItemNotFound { item } => write!(f, "Setting item not found: {}", item),
+            SettingError::Readonly { item } => write!(f, "Setting item is readonly: {}", item),
+            SettingError::InvalidValue { item, message } => {
+                write!(f, "Invalid value for `{}`: {}", item, message)
+            }
+            SettingError::InvalidProfile { message } => {
+                write!(f, "Invalid settings profile: {}", message)
+            }
+            SettingError::InvalidInstance { message } => {
+                write!(f, "Invalid instance: {}", message)
+            }
+            SettingError::Io { message } => write!(f, "Failed to save setting: {}", message),
+            SettingError::Unavailable { message } => {
+                write!(f, "Setting is unavailable: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingError {}
@@ -0,0 +1,89 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use serde_json::Value;
+
+//设置实际生效的值改变时发送给前端的事件
+pub const SETTING_CHANGED_EVENT: &str = "setting-changed";
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SettingEvent {
+    //-1 is globle
+    pub id: i32,
+    pub item: String,
+    pub value: Value,
+}
+
+pub type Listener = Box<dyn Fn(&SettingEvent) + Send + Sync>;
+
+//设置项名字到实际生效的值
+pub type Snapshot = BTreeMap<String, Value>;
+
+//比较修改前后的值, 只为改变了的设置项生成事件
+pub fn diff(before: &HashMap<i32, Snapshot>, after: &HashMap<i32, Snapshot>) -> Vec<SettingEvent> {
+    let mut ids: Vec<_> = after.keys().copied().collect();
+    ids.sort();
+    let mut events = Vec::new();
+    for id in ids {
+        let old = before.get(&id);
+        for (item, value) in &after[&id] {
+            if old.and_then(|old| old.get(item)) != Some(value) {
+                events.push(SettingEvent {
+                    id,
+                    item: item.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn snapshot(items: &[(&str, Value)]) -> Snapshot {
+        items
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = HashMap::from([
+            (-1, snapshot(&[("java", json!(1)), ("memory", json!(2))])),
+            (0, snapshot(&[("java", json!(1)), ("memory", json!(4))])),
+        ]);
+        let after = HashMap::from([
+            (-1, snapshot(&[("java", json!(3)), ("memory", json!(2))])),
+            (0, snapshot(&[("java", json!(3)), ("memory", json!(4))])),
+            (1, snapshot(&[("java", json!(3))])),
+        ]);
+        let events = diff(&before, &after);
+        assert_eq!(
+            events,
+            vec![
+                SettingEvent {
+                    id: -1,
+                    item: "java".to_string(),
+                    value: json!(3)
+                },
+                SettingEvent {
+                    id: 0,
+                    item: "java".to_string(),
+                    value: json!(3)
+                },
+                SettingEvent {
+                    id: 1,
+                    item: "java".to_string(),
+                    value: json!(3)
+                },
+            ]
+        );
+        assert!(diff(&after, &after).is_empty());
+    }
+}
@@ -1,24 +1,64 @@
 use std::{
-    fs::File,
-    io::{Read, Write},
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    env,
+    fs::{self, File},
+    io::Read,
     path::{Path, PathBuf},
     process::{Command, Stdio},
 };
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use which::which;
 
 use crate::api::dirs;
+use crate::api::mc_version::{McVersion, McVersionKind};
 use crate::api::version::Version;
+use crate::api::version_req::VersionReq;
 
 use super::setting_trait::SettingTrait;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct JavaVersion {
     path: String,
+    #[serde(deserialize_with = "deserialize_java_version")]
     version: Version,
+    #[serde(default)]
+    vendor: Option<String>,
+    #[serde(default)]
+    arch: Option<String>,
+    //完整的运行时版本, 如 17.0.10+7
+    #[serde(default)]
+    runtime_version: Option<String>,
+}
+
+impl JavaVersion {
+    //优先读取 java home 下的 release 文件, 没有时才启动 java -version
+    fn probe(java: &Path) -> Result<Self> {
+        let path = java.to_string_lossy().to_string();
+        if let Some(release) = java_home_of(java).and_then(|home| read_release(&home).ok()) {
+            if let Some(version) = release.get("JAVA_VERSION") {
+                return Ok(JavaVersion {
+                    path,
+                    version: Version::from_java_string(version).map_err(|e| anyhow::anyhow!(e))?,
+                    vendor: release.get("IMPLEMENTOR").cloned(),
+                    arch: release.get("OS_ARCH").cloned(),
+                    runtime_version: release.get("JAVA_RUNTIME_VERSION").cloned(),
+                });
+            }
+        }
+
+        let version = get_java_version(&java.to_path_buf())?;
+        Ok(JavaVersion {
+            path,
+            version: Version::from_java_string(&version).map_err(|e| anyhow::anyhow!(e))?,
+            vendor: None,
+            arch: None,
+            runtime_version: None,
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -36,28 +76,259 @@ impl JavaVersions {
     }
 
     pub fn add_version(&mut self, path: String, version: Version) {
-        self.versions.push(JavaVersion { path, version });
+        self.versions.push(JavaVersion {
+            path,
+            version,
+            vendor: None,
+            arch: None,
+            runtime_version: None,
+        });
     }
 
     //获取环境变量中java的版本
     pub fn load_path_versions() -> Self {
         let mut versions = JavaVersions::new();
-        let v = which("java");
-        if v.is_ok() {
-            let path = v.unwrap();
-            let version = get_java_version(&path);
-            if version.is_err() {
-                return versions;
+        if let Ok(path) = which("java") {
+            if let Ok(java_version) = JavaVersion::probe(&path) {
+                versions.versions.push(java_version);
             }
-            let version = Version::from_string(&version.unwrap(), Some(&vec!['\"']));
-            if version.is_err() {
-                return versions;
-            }
-            versions.add_version(path.to_string_lossy().to_string(), version.unwrap());
         }
         versions
     }
 
+    //扫描 PATH 以及常见的 java 安装位置
+    pub fn scan_versions() -> Self {
+        let mut versions = JavaVersions::load_path_versions();
+        versions.add_java_homes(java_home_candidates());
+        //新的在前
+        versions.versions.sort_by(|a, b| b.version.cmp(&a.version));
+        versions
+    }
+
+    //把每个 java home 下的 java 加入列表，按真实路径去重
+    pub fn add_java_homes(&mut self, homes: Vec<PathBuf>) {
+        let mut seen: HashSet<PathBuf> = self
+            .versions
+            .iter()
+            .map(|v| canonical(Path::new(&v.path)))
+            .collect();
+        for home in homes {
+            let Some(java) = java_executable(&home) else {
+                continue;
+            };
+            if !seen.insert(canonical(&java)) {
+                continue;
+            }
+            if let Ok(java_version) = JavaVersion::probe(&java) {
+                self.versions.push(java_version);
+            }
+        }
+    }
+
+    //手动添加一个 java, 可以是 java 可执行文件或 java home
+    pub fn add_java(&mut self, path: &Path) -> Result<()> {
+        let java = if path.is_dir() {
+            java_executable(path).ok_or_else(|| {
+                anyhow::anyhow!("No java executable found in `{}`", path.display())
+            })?
+        } else {
+            path.to_path_buf()
+        };
+        if !is_executable(&java) {
+            bail!("`{}` is not an executable file", java.display());
+        }
+        let canonical_java = canonical(&java);
+        if self
+            .versions
+            .iter()
+            .any(|v| canonical(Path::new(&v.path)) == canonical_java)
+        {
+            bail!("`{}` has already been added", java.display());
+        }
+        let java_version = JavaVersion::probe(&java)
+            .map_err(|e| anyhow::anyhow!("`{}` is not a valid java: {}", java.display(), e))?;
+        self.versions.push(java_version);
+        Ok(())
+    }
+
+    //选中项保持指向同一个 java, 选中项改变时返回 true:
+    //删除选中的 java 时改为选择大版本最接近的, 原来的选中项无效时改为选择第一个
+    pub fn remove(&mut self, index: usize) -> Result<bool> {
+        let len = self.versions.len();
+        if index >= len {
+            bail!(
+                "Java index {} out of range, {} versions available",
+                index,
+                len
+            );
+        }
+        let removed = self.versions.remove(index);
+        let select = self.select as usize;
+        if select >= len {
+            self.select = 0;
+            return Ok(true);
+        }
+        match index.cmp(&select) {
+            Ordering::Less => {
+                self.select -= 1;
+                Ok(false)
+            }
+            Ordering::Greater => Ok(false),
+            Ordering::Equal => {
+                //没有更新的 java 时选择第一个
+                self.select = self
+                    .resolve(removed.version.major())
+                    .ok()
+                    .and_then(|selection| u8::try_from(selection.index).ok())
+                    .unwrap_or(0);
+                Ok(true)
+            }
+        }
+    }
+
+    pub fn select(&mut self, index: usize) -> Result<()> {
+        if index >= self.versions.len() {
+            bail!(
+                "Java index {} out of range, {} versions available",
+                index,
+                self.versions.len()
+            );
+        }
+        self.select = u8::try_from(index)?;
+        Ok(())
+    }
+
+    //重新扫描并合并到现有列表, 手动添加和安装的 java 只要还能运行就保留, 尽量保留原来选中的 java
+    pub fn rescan(&mut self) {
+        let selected = self
+            .versions
+            .get(self.select as usize)
+            .map(|v| canonical(Path::new(&v.path)));
+        //重新读取版本, 去掉已经不存在的 java
+        let mut versions: Vec<JavaVersion> = self
+            .versions
+            .iter()
+            .filter_map(|v| JavaVersion::probe(Path::new(&v.path)).ok())
+            .collect();
+        let mut seen: HashSet<PathBuf> = versions
+            .iter()
+            .map(|v| canonical(Path::new(&v.path)))
+            .collect();
+        for java_version in JavaVersions::scan_versions().versions {
+            if seen.insert(canonical(Path::new(&java_version.path))) {
+                versions.push(java_version);
+            }
+        }
+        self.select = selected
+            .and_then(|selected| {
+                versions
+                    .iter()
+                    .position(|v| canonical(Path::new(&v.path)) == selected)
+            })
+            .and_then(|index| u8::try_from(index).ok())
+            .unwrap_or(0);
+        self.versions = versions;
+    }
+
+    //按游戏需要的 java 大版本挑选最合适的 java
+    //优先大版本完全相同的, 否则选比要求新的里面大版本最小的, 同一大版本取最新的
+    pub fn resolve(&self, required_major: u32) -> Result<JavaSelection> {
+        let best = self
+            .versions
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.version.major() >= required_major)
+            .min_by(|(_, a), (_, b)| {
+                a.version
+                    .major()
+                    .cmp(&b.version.major())
+                    .then_with(|| b.version.cmp(&a.version))
+            });
+        let Some((index, java_version)) = best else {
+            let installed: Vec<String> = self
+                .versions
+                .iter()
+                .map(|v| v.version.major().to_string())
+                .collect();
+            bail!(
+                "No compatible java installed: Java {} or newer is required, installed: [{}]",
+                required_major,
+                installed.join(", ")
+            );
+        };
+        let major = java_version.version.major();
+        let reason = if major == required_major {
+            format!(
+                "Java {} is required, selected the newest installed Java {} ({})",
+                required_major, major, java_version.version
+            )
+        } else {
+            format!(
+                "Java {} is required but not installed, selected the closest newer Java {} ({})",
+                required_major, major, java_version.version
+            )
+        };
+        Ok(JavaSelection {
+            index,
+            path: java_version.path.clone(),
+            version: java_version.version.clone(),
+            reason,
+        })
+    }
+
+    //优先使用用户选中的 java, 选中的大版本太低时才按 resolve 挑选
+    pub fn resolve_selected(&self, required_major: u32) -> Result<JavaSelection> {
+        let index = self.select as usize;
+        match self.versions.get(index) {
+            Some(java_version) if java_version.version.major() >= required_major => {
+                Ok(JavaSelection {
+                    index,
+                    path: java_version.path.clone(),
+                    version: java_version.version.clone(),
+                    reason: format!(
+                        "Java {} is required, using the selected Java {} ({})",
+                        required_major,
+                        java_version.version.major(),
+                        java_version.version
+                    ),
+                })
+            }
+            _ => self.resolve(required_major),
+        }
+    }
+
+    //选中游戏版本需要的 java, game_json 为版本 json, 没有时使用内置表
+    pub fn auto_select(
+        &mut self,
+        minecraft_version: &str,
+        game_json: Option<&Value>,
+    ) -> Result<JavaSelection> {
+        let required = required_java_major(minecraft_version, game_json)?;
+        let selection = self.resolve(required)?;
+        self.select(selection.index)?;
+        Ok(selection)
+    }
+
+    //导入后的 java 不满足配置文件的要求时返回提示, 配置文件中没有 java 时返回 None
+    pub fn import_warning(value: &Value, imported: &Self) -> Option<String> {
+        let wanted = ExportedJava::parse(value.clone()).ok()??;
+        if imported.find_exported(&wanted).is_some() {
+            return None;
+        }
+        Some(format!(
+            "Java {} or newer is not installed, keeping the current java",
+            wanted.version.major()
+        ))
+    }
+
+    fn find_exported(&self, wanted: &ExportedJava) -> Option<usize> {
+        let major = wanted.version.major();
+        let same_vendor = self.versions.iter().position(|java| {
+            java.version.major() == major && wanted.vendor.is_some() && java.vendor == wanted.vendor
+        });
+        same_vendor.or_else(|| self.resolve(major).ok().map(|selection| selection.index))
+    }
+
     fn load_file_version() -> Result<Self> {
         let config_dir = dirs::get_config_dirs()?;
 
@@ -67,13 +338,70 @@ impl JavaVersions {
             let mut contents = String::new();
             file.read_to_string(&mut contents)?;
             let java_versions: JavaVersions = serde_json::from_str(&contents)?;
-            return Ok(java_versions);
+            Ok(java_versions)
         } else {
-            return Ok(JavaVersions::load_path_versions());
+            Ok(JavaVersions::scan_versions())
         }
     }
 }
 
+#[derive(Debug, Serialize, Clone)]
+pub struct JavaSelection {
+    pub index: usize,
+    pub path: String,
+    pub version: Version,
+    //为什么选了这个 java, 给界面展示用
+    pub reason: String,
+}
+
+//没有 javaVersion 的版本 json 按正式版本号查找需要的 java 大版本
+const JAVA_REQUIREMENTS: [(&str, u32); 4] = [
+    ("<1.17", 8),
+    ("~1.17", 16),
+    (">=1.18 <1.20.5", 17),
+    (">=1.20.5", 21),
+];
+//表中最低的要求, 用于无法推断版本的 jar
+pub const LOWEST_JAVA_MAJOR: u32 = JAVA_REQUIREMENTS[0].1;
+
+//游戏版本需要的 java 大版本, 优先使用版本 json 中的 javaVersion.majorVersion
+pub fn required_java_major(minecraft_version: &str, game_json: Option<&Value>) -> Result<u32> {
+    if let Some(major) = game_json
+        .and_then(|json| json.get("javaVersion"))
+        .and_then(|java| java.get("majorVersion"))
+        .and_then(|major| major.as_u64())
+    {
+        return Ok(u32::try_from(major)?);
+    }
+
+    let minecraft_version = McVersion::parse(minecraft_version);
+    let version = match minecraft_version.kind() {
+        McVersionKind::Beta(_)
+        | McVersionKind::Alpha(_)
+        | McVersionKind::Infdev(_)
+        | McVersionKind::Indev(_)
+        | McVersionKind::Classic(_)
+        | McVersionKind::PreClassic(_) => return Ok(8),
+        _ => minecraft_version
+            .release_target()
+            .ok_or_else(|| anyhow::anyhow!("Unknown minecraft version `{}`", minecraft_version))?,
+    };
+    let major = JAVA_REQUIREMENTS
+        .iter()
+        .find(|(req, _)| VersionReq::parse(req).is_ok_and(|req| req.matches(&version)))
+        .map(|(_, major)| *major)
+        .ok_or_else(|| anyhow::anyhow!("Unknown minecraft version `{}`", minecraft_version))?;
+    Ok(major)
+}
+
+//兼容旧文件中按普通方式解析保存的 java 版本号
+fn deserialize_java_version<'de, D>(deserializer: D) -> Result<Version, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Version::deserialize(deserializer)?.into_java())
+}
+
 impl SettingTrait for JavaVersions {
     fn read(json: Option<Value>) -> Result<Self> {
         match json {
@@ -92,10 +420,274 @@ impl SettingTrait for JavaVersions {
         let json = serde_json::to_value(self)?;
         Ok(json)
     }
-    fn receive(&mut self, value: Vec<String>) -> Result<()> {
-        todo!();
-        Ok(())
+    //{"command": "add", "path": ...} 等, 旧版本的 ["add", path] 字符串数组仍然可用
+    fn receive(&mut self, value: Value) -> Result<()> {
+        match JavaCommand::parse(value)? {
+            JavaCommand::Add { path } => self.add_java(&path),
+            //选中项的改变通过修改后的值返回给前端
+            JavaCommand::Remove { index } => self.remove(index).map(|_| ()),
+            JavaCommand::Select { index } => self.select(index),
+            JavaCommand::Rescan {} => {
+                self.rescan();
+                Ok(())
+            }
+            JavaCommand::Auto { minecraft_version } => {
+                self.auto_select(&minecraft_version, None)?;
+                Ok(())
+            }
+        }
+    }
+    //只导出选中的 java 版本, 路径在导入时重新查找
+    fn export(&self) -> Result<Value> {
+        Ok(match self.versions.get(self.select as usize) {
+            Some(java) => serde_json::to_value(ExportedJava {
+                version: java.version.clone(),
+                vendor: java.vendor.clone(),
+            })?,
+            None => Value::Null,
+        })
+    }
+    //在当前已安装的 java 中选择和导出时相同大版本的, 优先相同厂商
+    //这台机器上没有合适的 java 时保持当前的选择, 由 import_warning 提示
+    fn import(value: Value, current: Option<&Self>) -> Result<Self> {
+        let wanted = ExportedJava::parse(value)?;
+        let mut java_versions = match current {
+            Some(current) => current.clone(),
+            None => JavaVersions::load_file_version()?,
+        };
+        if let Some(index) = wanted.and_then(|wanted| java_versions.find_exported(&wanted)) {
+            java_versions.select(index)?;
+        }
+        Ok(java_versions)
+    }
+    //路径只在这台机器上有效
+    fn machine_specific() -> bool {
+        true
+    }
+    fn schema() -> Value {
+        let optional_string = serde_json::json!({ "type": ["string", "null"] });
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "versions": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "path": { "type": "string" },
+                            "version": { "type": "string" },
+                            "vendor": optional_string,
+                            "arch": optional_string,
+                            "runtime_version": optional_string,
+                        },
+                    },
+                },
+                "select": { "type": "integer", "minimum": 0, "maximum": u8::MAX },
+            },
+            //receive 接受的命令
+            "commands": {
+                "add": { "path": { "type": "string" } },
+                "remove": { "index": { "type": "integer", "minimum": 0 } },
+                "select": { "index": { "type": "integer", "minimum": 0 } },
+                "rescan": {},
+                "auto": { "minecraft_version": { "type": "string" } },
+            },
+        })
+    }
+}
+
+//导出的 java 设置, 不包含路径
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportedJava {
+    #[serde(deserialize_with = "deserialize_java_version")]
+    version: Version,
+    #[serde(default)]
+    vendor: Option<String>,
+}
+
+impl ExportedJava {
+    fn parse(value: Value) -> Result<Option<Self>> {
+        if value.get("versions").is_some() {
+            //完整的设置文件也可以导入, 其中的路径在这台机器上不一定有效
+            let full: JavaVersions = serde_json::from_value(value)?;
+            return Ok(full
+                .versions
+                .get(full.select as usize)
+                .map(|java| ExportedJava {
+                    version: java.version.clone(),
+                    vendor: java.vendor.clone(),
+                }));
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "command", rename_all = "lowercase", deny_unknown_fields)]
+enum JavaCommand {
+    Add { path: PathBuf },
+    Remove { index: usize },
+    Select { index: usize },
+    Rescan {},
+    Auto { minecraft_version: String },
+}
+
+impl JavaCommand {
+    fn parse(value: Value) -> Result<Self> {
+        if value.is_array() {
+            let args: Vec<String> = serde_json::from_value(value)
+                .map_err(|e| anyhow::anyhow!("Invalid java setting command: {}", e))?;
+            return Self::from_args(&args);
+        }
+        serde_json::from_value(value)
+            .map_err(|e| anyhow::anyhow!("Invalid java setting command: {}", e))
+    }
+
+    //兼容旧的 Vec<String> 参数
+    fn from_args(value: &[String]) -> Result<Self> {
+        let Some((op, args)) = value.split_first() else {
+            bail!("Empty java setting command");
+        };
+        Ok(match (op.as_str(), args) {
+            ("add", [path]) => JavaCommand::Add {
+                path: PathBuf::from(path),
+            },
+            ("remove", [index]) => JavaCommand::Remove {
+                index: parse_index(index)?,
+            },
+            ("select", [index]) => JavaCommand::Select {
+                index: parse_index(index)?,
+            },
+            ("rescan", []) => JavaCommand::Rescan {},
+            ("auto", [minecraft_version]) => JavaCommand::Auto {
+                minecraft_version: minecraft_version.clone(),
+            },
+            _ => bail!("Invalid java setting command: {:?}", value),
+        })
+    }
+}
+
+//所有可能是 java home 的目录
+fn java_home_candidates() -> Vec<PathBuf> {
+    let mut homes = Vec::new();
+    if let Some(java_home) = env::var_os("JAVA_HOME") {
+        homes.push(PathBuf::from(java_home));
+    }
+
+    let mut roots: Vec<PathBuf> = Vec::new();
+    if let Some(home) = dirs_next::home_dir() {
+        roots.push(home.join(".sdkman").join("candidates").join("java"));
+        roots.push(home.join(".asdf").join("installs").join("java"));
+        roots.push(home.join(".jabba").join("jdk"));
+        #[cfg(target_os = "macos")]
+        roots.push(
+            home.join("Library")
+                .join("Java")
+                .join("JavaVirtualMachines"),
+        );
+    }
+    if let Ok(runtime) = dirs::get_runtime_dirs() {
+        roots.push(runtime);
     }
+    #[cfg(target_os = "linux")]
+    roots.push(PathBuf::from("/usr/lib/jvm"));
+    #[cfg(target_os = "macos")]
+    roots.push(PathBuf::from("/Library/Java/JavaVirtualMachines"));
+    #[cfg(target_os = "windows")]
+    for var in ["ProgramFiles", "ProgramFiles(x86)"] {
+        if let Some(program_files) = env::var_os(var) {
+            let program_files = PathBuf::from(program_files);
+            for vendor in ["Java", "Eclipse Adoptium", "Zulu", "Microsoft", "BellSoft"] {
+                roots.push(program_files.join(vendor));
+            }
+        }
+    }
+    for root in roots {
+        homes.extend(child_dirs(&root, |_| true));
+    }
+
+    #[cfg(unix)]
+    homes.extend(child_dirs(Path::new("/opt"), |name| {
+        name.contains("jdk") || name.contains("jre")
+    }));
+
+    homes
+}
+
+fn child_dirs(root: &Path, filter: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .filter(|p| {
+            p.file_name()
+                .map(|n| filter(&n.to_string_lossy().to_lowercase()))
+                .unwrap_or(false)
+        })
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+//java home 下的 java 可执行文件, macOS 的 jdk 多一层 Contents/Home
+fn java_executable(home: &Path) -> Option<PathBuf> {
+    let exe = if cfg!(windows) { "java.exe" } else { "java" };
+    [home.to_path_buf(), home.join("Contents").join("Home")]
+        .into_iter()
+        .map(|h| h.join("bin").join(exe))
+        .find(|p| p.is_file())
+}
+
+fn parse_index(index: &str) -> Result<usize> {
+    index
+        .trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid java index `{}`", index))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+//bin/java 的上两级目录, 会先解析符号链接(如 /usr/bin/java)
+fn java_home_of(java: &Path) -> Option<PathBuf> {
+    canonical(java).parent()?.parent().map(|p| p.to_path_buf())
+}
+
+//release 文件每行形如 JAVA_VERSION="17.0.10"
+fn read_release(home: &Path) -> Result<HashMap<String, String>> {
+    let content = fs::read_to_string(home.join("release"))?;
+    Ok(parse_release(&content))
+}
+
+fn parse_release(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.trim().to_string(),
+                value.trim().trim_matches('"').to_string(),
+            )
+        })
+        .collect()
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
 }
 
 fn get_java_version(java_path: &PathBuf) -> Result<String> {
@@ -127,4 +719,340 @@ mod tests {
         assert!(!java_versions.versions.is_empty());
         println!("{:#?}", java_versions);
     }
+
+    //生成一个假的 jdk, java 只会把版本输出到 stderr
+    #[cfg(unix)]
+    fn fake_jdk(home: &Path, version: &str) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let bin = home.join("bin");
+        fs::create_dir_all(&bin).unwrap();
+        let java = bin.join("java");
+        fs::write(
+            &java,
+            format!(
+                "#!/bin/sh\necho 'openjdk version \"{}\" 2024-01-16 LTS' >&2\n",
+                version
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&java, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_parse_release() {
+        let release = parse_release(
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_RUNTIME_VERSION=\"17.0.10+7\"\nJAVA_VERSION=\"17.0.10\"\nOS_ARCH=\"aarch64\"\nSOURCE=\"\"\n",
+        );
+        assert_eq!(release["IMPLEMENTOR"], "Eclipse Adoptium");
+        assert_eq!(release["JAVA_RUNTIME_VERSION"], "17.0.10+7");
+        assert_eq!(release["JAVA_VERSION"], "17.0.10");
+        assert_eq!(release["OS_ARCH"], "aarch64");
+        assert_eq!(release["SOURCE"], "");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_prefers_release_file() {
+        let root = tempfile::tempdir().unwrap();
+        let home = root.path().join("jdk-21");
+        fake_jdk(&home, "1.8.0_281");
+        fs::write(
+            home.join("release"),
+            "IMPLEMENTOR=\"Oracle Corporation\"\nJAVA_RUNTIME_VERSION=\"21.0.2+13-LTS-58\"\nJAVA_VERSION=\"21.0.2\"\nOS_ARCH=\"x86_64\"\n",
+        )
+        .unwrap();
+
+        let java_version = JavaVersion::probe(&home.join("bin").join("java")).unwrap();
+        assert_eq!(java_version.version.to_string(), "21.0.2");
+        assert_eq!(java_version.vendor.as_deref(), Some("Oracle Corporation"));
+        assert_eq!(java_version.arch.as_deref(), Some("x86_64"));
+        assert_eq!(
+            java_version.runtime_version.as_deref(),
+            Some("21.0.2+13-LTS-58")
+        );
+    }
+
+    fn to_command(command: &[&str]) -> Value {
+        serde_json::json!(command)
+    }
+
+    fn three_versions() -> JavaVersions {
+        let mut java_versions = JavaVersions::new();
+        for (path, version) in [("a", "8"), ("b", "17"), ("c", "21")] {
+            java_versions.add_version(
+                path.to_string(),
+                Version::from_string(&version.to_string(), None).unwrap(),
+            );
+        }
+        java_versions
+    }
+
+    #[test]
+    fn test_receive_select_and_remove() {
+        let mut java_versions = three_versions();
+        java_versions.receive(to_command(&["select", "2"])).unwrap();
+        assert_eq!(java_versions.select, 2);
+        assert!(java_versions.receive(to_command(&["select", "3"])).is_err());
+        assert!(java_versions
+            .receive(to_command(&["select", "-1"]))
+            .is_err());
+        assert_eq!(java_versions.select, 2);
+
+        //删除选中项之前的元素, 选中项跟着移动
+        java_versions.receive(to_command(&["remove", "0"])).unwrap();
+        assert_eq!(java_versions.select, 1);
+        assert_eq!(java_versions.versions[1].path, "c");
+
+        //删除选中的最后一项
+        java_versions.receive(to_command(&["remove", "1"])).unwrap();
+        assert_eq!(java_versions.select, 0);
+        assert!(java_versions.receive(to_command(&["remove", "1"])).is_err());
+        java_versions.receive(to_command(&["remove", "0"])).unwrap();
+        assert!(java_versions.versions.is_empty());
+        assert_eq!(java_versions.select, 0);
+    }
+
+    #[test]
+    fn test_remove_selected() {
+        let mut java_versions = three_versions();
+        java_versions.add_version(
+            "d".to_string(),
+            Version::from_string(&"17.0.9".to_string(), None).unwrap(),
+        );
+
+        //删除选中项之后的元素, 选中项不变
+        java_versions.select(1).unwrap();
+        assert!(!java_versions.remove(3).unwrap());
+        assert_eq!(java_versions.select, 1);
+
+        //删除选中项之前的元素, 选中项跟着移动
+        assert!(!java_versions.remove(0).unwrap());
+        assert_eq!(
+            java_versions.versions[java_versions.select as usize].path,
+            "b"
+        );
+
+        //删除选中的 java, 改为选择大版本最接近的
+        java_versions.add_version(
+            "e".to_string(),
+            Version::from_string(&"25".to_string(), None).unwrap(),
+        );
+        assert!(java_versions.remove(0).unwrap());
+        assert_eq!(
+            java_versions.versions[java_versions.select as usize].path,
+            "c"
+        );
+
+        //原来的选中项超出范围时改为选择第一个
+        java_versions.select = 5;
+        assert!(java_versions.remove(1).unwrap());
+        assert_eq!(java_versions.select, 0);
+        assert_eq!(java_versions.versions.len(), 1);
+    }
+
+    #[test]
+    fn test_receive_invalid_commands() {
+        let mut java_versions = three_versions();
+        assert!(java_versions.receive(serde_json::json!([])).is_err());
+        assert!(java_versions.receive(to_command(&["unknown"])).is_err());
+        assert!(java_versions.receive(to_command(&["select"])).is_err());
+        assert!(java_versions
+            .receive(to_command(&["select", "1", "2"]))
+            .is_err());
+        assert!(java_versions
+            .receive(to_command(&["add", "/path/does/not/exist/java"]))
+            .is_err());
+        assert_eq!(java_versions.versions.len(), 3);
+    }
+
+    #[test]
+    fn test_receive_typed_commands() {
+        let mut java_versions = three_versions();
+        java_versions
+            .receive(serde_json::json!({ "command": "select", "index": 1 }))
+            .unwrap();
+        assert_eq!(java_versions.select, 1);
+        java_versions
+            .receive(serde_json::json!({ "command": "remove", "index": 0 }))
+            .unwrap();
+        assert_eq!(java_versions.versions.len(), 2);
+
+        //类型不对的值在修改前就被拒绝
+        for value in [
+            serde_json::json!({ "command": "select", "index": "1" }),
+            serde_json::json!({ "command": "select", "index": -1 }),
+            serde_json::json!({ "command": "select" }),
+            serde_json::json!({ "command": "rescan", "index": 1 }),
+            serde_json::json!({ "command": "unknown" }),
+            serde_json::json!("select"),
+            serde_json::json!(["select", 1]),
+        ] {
+            assert!(java_versions.receive(value).is_err());
+        }
+        assert_eq!(java_versions.select, 0);
+
+        assert_eq!(
+            JavaCommand::parse(serde_json::json!(["auto", "1.20.1"])).unwrap(),
+            JavaCommand::parse(
+                serde_json::json!({ "command": "auto", "minecraft_version": "1.20.1" })
+            )
+            .unwrap()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_receive_add() {
+        let root = tempfile::tempdir().unwrap();
+        let home = root.path().join("jdk-17");
+        fake_jdk(&home, "17.0.10");
+        fs::write(root.path().join("not-java"), "").unwrap();
+
+        let mut java_versions = JavaVersions::new();
+        let java = home.join("bin").join("java");
+        java_versions
+            .receive(to_command(&["add", &java.to_string_lossy()]))
+            .unwrap();
+        assert_eq!(java_versions.versions.len(), 1);
+        //同一个 java 不能重复添加
+        assert!(java_versions
+            .receive(to_command(&["add", &home.to_string_lossy()]))
+            .is_err());
+        assert!(java_versions
+            .receive(to_command(&[
+                "add",
+                &root.path().join("not-java").to_string_lossy()
+            ]))
+            .is_err());
+        assert_eq!(java_versions.versions.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rescan_keeps_manual_java() {
+        let root = tempfile::tempdir().unwrap();
+        let jdk_17 = root.path().join("jdk-17");
+        let jdk_21 = root.path().join("jdk-21");
+        fake_jdk(&jdk_17, "17.0.10");
+        fake_jdk(&jdk_21, "21.0.2");
+
+        let mut java_versions = JavaVersions::new();
+        java_versions.add_java(&jdk_17).unwrap();
+        java_versions.add_java(&jdk_21).unwrap();
+        java_versions.select(1).unwrap();
+        //删除的 java 在重新扫描后去掉, 还能运行的手动添加的 java 保留并保持选中
+        fs::remove_dir_all(&jdk_17).unwrap();
+        java_versions.rescan();
+
+        let paths: Vec<&str> = java_versions
+            .versions
+            .iter()
+            .map(|v| v.path.as_str())
+            .collect();
+        let java_21 = jdk_21.join("bin").join("java");
+        assert!(!paths.contains(&jdk_17.join("bin").join("java").to_str().unwrap()));
+        assert_eq!(paths[0], java_21.to_str().unwrap());
+        assert_eq!(java_versions.select, 0);
+        assert_eq!(java_versions.versions[0].version.to_string(), "21.0.2");
+    }
+
+    #[test]
+    fn test_legacy_java_version_file() {
+        let json = serde_json::json!({
+            "versions": [
+                { "path": "a", "version": { "value": "1.8.0 _281" } },
+                { "path": "b", "version": { "value": "17.0.10 2024-01-16 LTS" } }
+            ],
+            "select": 0
+        });
+        let java_versions = JavaVersions::read(Some(json)).unwrap();
+        assert_eq!(java_versions.versions[0].version.to_string(), "8.0.281");
+        assert_eq!(java_versions.versions[0].version.major(), 8);
+        assert!(java_versions.versions[0].version < java_versions.versions[1].version);
+        //日期和 LTS 不能当成预发布版本, 否则比 17.0.10 小
+        let dated = &java_versions.versions[1].version;
+        assert_eq!(dated, &Version::from_java_string("17.0.10").unwrap());
+        assert_eq!(dated.pre_release(), None);
+    }
+
+    #[test]
+    fn test_required_java_major() {
+        assert_eq!(required_java_major("1.12.2", None).unwrap(), 8);
+        assert_eq!(required_java_major("1.16.5", None).unwrap(), 8);
+        assert_eq!(required_java_major("1.17.1", None).unwrap(), 16);
+        assert_eq!(required_java_major("1.18", None).unwrap(), 17);
+        assert_eq!(required_java_major("1.20.4", None).unwrap(), 17);
+        assert_eq!(required_java_major("1.20.5", None).unwrap(), 21);
+        assert_eq!(required_java_major("1.21.1", None).unwrap(), 21);
+        assert_eq!(required_java_major("24w14a", None).unwrap(), 21);
+        assert_eq!(required_java_major("1.21-pre3", None).unwrap(), 21);
+        assert_eq!(required_java_major("b1.7.3", None).unwrap(), 8);
+        assert!(required_java_major("not a version", None).is_err());
+
+        let game_json = serde_json::json!({
+            "id": "1.20.4",
+            "javaVersion": { "component": "java-runtime-gamma", "majorVersion": 17 }
+        });
+        assert_eq!(
+            required_java_major("whatever", Some(&game_json)).unwrap(),
+            17
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut java_versions = JavaVersions::new();
+        for (path, version) in [
+            ("8", "java version \"1.8.0_281\""),
+            ("17.0.2", "17.0.2"),
+            ("17.0.10", "17.0.10"),
+            ("21", "21.0.2"),
+        ] {
+            java_versions.add_version(
+                path.to_string(),
+                Version::from_java_string(version).unwrap(),
+            );
+        }
+
+        let selection = java_versions.resolve(8).unwrap();
+        assert_eq!(selection.path, "8");
+        let selection = java_versions.resolve(17).unwrap();
+        assert_eq!(selection.path, "17.0.10");
+        let selection = java_versions.resolve(16).unwrap();
+        assert_eq!(selection.path, "17.0.10");
+        assert_eq!(java_versions.resolve(9).unwrap().path, "17.0.10");
+        assert!(selection.reason.contains("newer"));
+        assert!(java_versions.resolve(25).is_err());
+
+        let selection = java_versions.auto_select("1.21", None).unwrap();
+        assert_eq!(selection.path, "21");
+        assert_eq!(java_versions.select, 3);
+        assert!(JavaVersions::new().resolve(8).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_add_java_homes() {
+        let root = tempfile::tempdir().unwrap();
+        fake_jdk(&root.path().join("jdk-17"), "17.0.10");
+        fake_jdk(
+            &root.path().join("jdk-21").join("Contents").join("Home"),
+            "21.0.2",
+        );
+        fs::create_dir_all(root.path().join("not-a-jdk")).unwrap();
+        std::os::unix::fs::symlink(root.path().join("jdk-17"), root.path().join("current"))
+            .unwrap();
+
+        let mut java_versions = JavaVersions::new();
+        java_versions.add_java_homes(child_dirs(root.path(), |_| true));
+
+        let mut found: Vec<String> = java_versions
+            .versions
+            .iter()
+            .map(|v| v.version.to_string())
+            .collect();
+        found.sort();
+        assert_eq!(found, vec!["17.0.10", "21.0.2"]);
+    }
 }
@@ -0,0 +1,170 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+//把版本 i + 1 的设置升级到 i + 2, 通过 #[setting(migrations = ...)] 交给 derive
+pub type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+//文件中当前版本不认识的内容, 写回时保留, 降级后再升级不会丢数据
+#[derive(Default, Clone)]
+pub struct Preserved {
+    pub schema_version: u32,
+    pub extra: Map<String, Value>,
+}
+
+impl Preserved {
+    pub fn apply(&self, value: &mut Value) {
+        let Some(object) = value.as_object_mut() else {
+            return;
+        };
+        for (key, item) in &self.extra {
+            object.entry(key.clone()).or_insert_with(|| item.clone());
+        }
+        //文件来自更新的版本时保留它的版本号, 避免再次迁移
+        let version = object
+            .get("schema_version")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        if u64::from(self.schema_version) > version {
+            object.insert("schema_version".to_string(), self.schema_version.into());
+        }
+    }
+}
+
+//迁移前复制一份 `<name>.v<version>.bak`
+pub fn backup_before_migration(path: &Path, version: u32) -> io::Result<PathBuf> {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".v{}.bak", version));
+    let backup_path = path.with_file_name(file_name);
+    fs::copy(path, &backup_path)?;
+    Ok(backup_path)
+}
+
+//迁移后立即写回, 旧文件先备份, 没有迁移时什么都不做
+pub fn save_migrated(
+    path: &Path,
+    migrated_from: Option<u32>,
+    save: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    if let Some(version) = migrated_from {
+        backup_before_migration(path, version)?;
+        save()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::settings::{
+        java_versions::JavaVersions, setting_manager::SettingManager, setting_trait::SettingTrait,
+    };
+
+    #[derive(setting_derive::Setting)]
+    #[setting(migrations = TEST_MIGRATIONS)]
+    struct TestSettings {
+        java: JavaVersions,
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[rename_jdk, drop_legacy];
+
+    fn rename_jdk(json: &mut Map<String, Value>) -> Result<()> {
+        if let Some(jdk) = json.remove("jdk") {
+            json.insert("java".to_string(), jdk);
+        }
+        Ok(())
+    }
+
+    fn drop_legacy(json: &mut Map<String, Value>) -> Result<()> {
+        json.remove("legacy");
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate() {
+        assert_eq!(TestSettings::SCHEMA_VERSION, 3);
+
+        let mut json = json!({
+            "jdk": {"versions": [], "select": 0},
+            "legacy": true,
+            "theme": "dark",
+        });
+        assert_eq!(TestSettings::migrate(&mut json).unwrap(), Some(1));
+        assert_eq!(json["schema_version"], 3);
+        assert!(json.get("legacy").is_none());
+        assert_eq!(
+            TestSettings::unknown_fields(&json),
+            json!({"theme": "dark"}).as_object().unwrap().clone()
+        );
+        TestSettings::read(json.clone()).unwrap();
+        assert_eq!(TestSettings::migrate(&mut json).unwrap(), None);
+
+        //只执行还没执行过的迁移
+        let mut json = json!({"schema_version": 2, "jdk": 1, "legacy": 1});
+        assert_eq!(TestSettings::migrate(&mut json).unwrap(), Some(2));
+        assert_eq!(json, json!({"schema_version": 3, "jdk": 1}));
+
+        let mut json = json!({"schema_version": 0});
+        assert!(TestSettings::migrate(&mut json).is_err());
+        let mut json = json!({"schema_version": "2"});
+        assert!(TestSettings::migrate(&mut json).is_err());
+        let mut json = json!([]);
+        assert!(TestSettings::migrate(&mut json).is_err());
+    }
+
+    #[test]
+    fn test_newer_file() {
+        let mut json = json!({
+            "schema_version": 7,
+            "java": {"versions": [], "select": 0},
+            "future": [1, 2],
+        });
+        assert_eq!(TestSettings::migrate(&mut json).unwrap(), None);
+        let preserved = Preserved {
+            schema_version: TestSettings::schema_version(&json).unwrap(),
+            extra: TestSettings::unknown_fields(&json),
+        };
+
+        let setting = TestSettings::read(json).unwrap();
+        let mut saved = setting.save().unwrap();
+        assert_eq!(saved["schema_version"], 3);
+        preserved.apply(&mut saved);
+        assert_eq!(saved["schema_version"], 7);
+        assert_eq!(saved["future"], json!([1, 2]));
+    }
+
+    #[test]
+    fn test_backup_before_migration() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("setting.json");
+        fs::write(&path, "{}").unwrap();
+        let backup = backup_before_migration(&path, 1).unwrap();
+        assert_eq!(backup.file_name().unwrap(), "setting.json.v1.bak");
+        assert_eq!(fs::read_to_string(backup).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_unknown_fields_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("setting.json");
+        fs::write(
+            &path,
+            r#"{"java": {"versions": [], "select": 0}, "theme": "dark"}"#,
+        )
+        .unwrap();
+
+        let mut problems = Vec::new();
+        let manager = SettingManager::load(&path, &mut problems).unwrap();
+        assert!(problems.is_empty());
+        manager.save().unwrap();
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["theme"], "dark");
+        assert_eq!(saved["schema_version"], 1);
+    }
+}
@@ -1,4 +1,11 @@
+pub mod atomic_file;
+pub mod error;
+pub mod events;
 pub mod java_versions;
+pub mod migration;
+pub mod profile;
+pub mod recovery;
 pub mod setting;
 pub mod setting_manager;
 pub mod setting_trait;
+pub mod watcher;
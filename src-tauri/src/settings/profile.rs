@@ -0,0 +1,124 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const PROFILE_FORMAT: &str = "rev-launcher-settings-profile";
+//配置文件本身格式的版本, 和设置的 schema_version 无关
+pub const PROFILE_VERSION: u32 = 1;
+
+//导出的设置配置文件, 可以在其它机器或整合包中导入
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub format: String,
+    pub profile_version: u32,
+    pub kind: ProfileKind,
+    //导出时设置的 schema_version, 导入前用来迁移
+    pub schema_version: u32,
+    pub exported_at: String,
+    pub settings: Value,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileKind {
+    //全局设置
+    Global,
+    //整合包覆盖的设置
+    Instance,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    //只修改配置文件中有的设置项
+    Merge,
+    //配置文件中没有的设置项恢复默认值, 整合包则取消覆盖
+    Replace,
+}
+
+//导入前后实际生效的值不同的设置项
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ProfileChange {
+    pub item: String,
+    pub before: Value,
+    pub after: Value,
+    //没有完全按配置文件导入的原因, 如这台机器上没有需要的 java
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+impl Profile {
+    pub fn new(kind: ProfileKind, schema_version: u32, settings: Value) -> Self {
+        Profile {
+            format: PROFILE_FORMAT.to_string(),
+            profile_version: PROFILE_VERSION,
+            kind,
+            schema_version,
+            exported_at: Utc::now().to_rfc3339(),
+            settings,
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let profile: Profile = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if profile.format != PROFILE_FORMAT {
+            bail!("Not a settings profile: {}", path.display());
+        }
+        if profile.profile_version > PROFILE_VERSION {
+            bail!(
+                "Settings profile version {} is newer than supported version {}",
+                profile.profile_version,
+                PROFILE_VERSION
+            );
+        }
+        if !profile.settings.is_object() {
+            bail!("Settings in profile must be a json object");
+        }
+        Ok(profile)
+    }
+
+    //导出的文件由用户管理, 不需要保留 .bak 备份
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    //带上 schema_version 的设置, 可以直接交给 migrate
+    pub fn versioned_settings(&self) -> Value {
+        let mut settings = self.settings.clone();
+        if let Some(object) = settings.as_object_mut() {
+            object.insert(
+                "schema_version".to_string(),
+                serde_json::json!(self.schema_version),
+            );
+        }
+        settings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile.json");
+        let profile = Profile::new(ProfileKind::Global, 1, serde_json::json!({ "java": null }));
+        profile.write(&path).unwrap();
+        let read = Profile::read(&path).unwrap();
+        assert_eq!(read.kind, ProfileKind::Global);
+        assert_eq!(read.versioned_settings()["schema_version"], 1);
+
+        let mut newer = serde_json::to_value(&profile).unwrap();
+        newer["profile_version"] = serde_json::json!(PROFILE_VERSION + 1);
+        fs::write(&path, newer.to_string()).unwrap();
+        assert!(Profile::read(&path).is_err());
+
+        fs::write(&path, r#"{ "java": null }"#).unwrap();
+        assert!(Profile::read(&path).is_err());
+    }
+}
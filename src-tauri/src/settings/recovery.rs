@@ -0,0 +1,178 @@
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use serde::Serialize;
+
+//启动时无法恢复的错误, 其它问题都会记录成 StartupProblem 继续启动
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StartupError {
+    ConfigDir { message: String },
+    Io { path: String, message: String },
+}
+
+impl StartupError {
+    pub fn io(path: &Path, err: impl Display) -> Self {
+        StartupError::Io {
+            path: path.to_string_lossy().to_string(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl Display for StartupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartupError::ConfigDir { message } => {
+                write!(f, "Failed to open config directory: {}", message)
+            }
+            StartupError::Io { path, message } => {
+                write!(f, "Failed to load `{}`: {}", path, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StartupError {}
+
+//启动时自动修复的问题, 交给前端提示用户
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupProblem {
+    pub path: String,
+    pub message: String,
+    //损坏的文件被移动到的位置
+    pub quarantined: Option<String>,
+    //出问题的整合包, 全局设置为 None
+    pub instance: Option<i32>,
+    //无法恢复, 设置不可用
+    pub fatal: bool,
+}
+
+impl StartupProblem {
+    pub fn new(path: &Path, message: impl Display) -> Self {
+        StartupProblem {
+            path: path.to_string_lossy().to_string(),
+            message: message.to_string(),
+            quarantined: None,
+            instance: None,
+            fatal: false,
+        }
+    }
+
+    pub fn with_instance(mut self, id: i32) -> Self {
+        self.instance = Some(id);
+        self
+    }
+}
+
+impl From<&StartupError> for StartupProblem {
+    fn from(err: &StartupError) -> Self {
+        let path = match err {
+            StartupError::ConfigDir { .. } => "",
+            StartupError::Io { path, .. } => path,
+        };
+        StartupProblem {
+            fatal: true,
+            ..StartupProblem::new(Path::new(path), err)
+        }
+    }
+}
+
+//把损坏的文件改名为 `<name>.corrupt-<timestamp>`
+pub fn quarantine(path: &Path) -> io::Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let timestamp = Utc::now().format("%Y%m%d%H%M%S");
+    let mut target = path.with_file_name(format!("{}.corrupt-{}", file_name, timestamp));
+    //同一秒内多次隔离时加序号
+    let mut index = 1;
+    while target.exists() {
+        target = path.with_file_name(format!("{}.corrupt-{}-{}", file_name, timestamp, index));
+        index += 1;
+    }
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+//读取并解析文件, 解析失败时隔离文件并返回 None 让调用者使用默认值
+//文件不存在时也返回 None, 只有读写文件本身出错才返回 Err
+pub fn read_or_recover<T>(
+    path: &Path,
+    parse: impl FnOnce(&str) -> anyhow::Result<T>,
+    problems: &mut Vec<StartupProblem>,
+) -> io::Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    match parse(&content) {
+        Ok(value) => Ok(Some(value)),
+        Err(err) => {
+            let target = quarantine(path)?;
+            let mut problem = StartupProblem::new(path, format!("{:#}", err));
+            problem.quarantined = Some(target.to_string_lossy().to_string());
+            problems.push(problem);
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> anyhow::Result<Vec<i32>> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    #[test]
+    fn test_fatal_problem() {
+        let err = StartupError::io(Path::new("setting.json"), "denied");
+        let problem = StartupProblem::from(&err);
+        assert!(problem.fatal);
+        assert_eq!(problem.path, "setting.json");
+        assert_eq!(problem.message, "Failed to load `setting.json`: denied");
+    }
+
+    #[test]
+    fn test_read_or_recover() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("list.json");
+        let mut problems = Vec::new();
+
+        assert!(read_or_recover(&path, parse, &mut problems)
+            .unwrap()
+            .is_none());
+        fs::write(&path, "[1, 2]").unwrap();
+        assert_eq!(
+            read_or_recover(&path, parse, &mut problems).unwrap(),
+            Some(vec![1, 2])
+        );
+        assert!(problems.is_empty());
+
+        for _ in 0..2 {
+            fs::write(&path, "[1, ").unwrap();
+            assert!(read_or_recover(&path, parse, &mut problems)
+                .unwrap()
+                .is_none());
+        }
+        assert!(!path.exists());
+        assert_eq!(problems.len(), 2);
+        assert_ne!(problems[0].quarantined, problems[1].quarantined);
+        for problem in problems {
+            let quarantined = PathBuf::from(problem.quarantined.unwrap());
+            assert!(quarantined
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("list.json.corrupt-"));
+            assert_eq!(fs::read_to_string(quarantined).unwrap(), "[1, ");
+        }
+    }
+}
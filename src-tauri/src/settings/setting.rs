@@ -1,28 +1,78 @@
 use std::{
     collections::HashMap,
-    fs::File,
-    io::{Read, Write},
-    path::PathBuf,
-    str::FromStr,
-    sync::{OnceLock, RwLock},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock, RwLock},
 };
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::api::dirs;
 
-use super::setting_manager::{ModpackSettingManager, SettingManager};
+use super::{
+    atomic_file::write_atomic,
+    error::SettingError,
+    events::{self, Listener, SettingEvent, Snapshot},
+    java_versions::JavaVersions,
+    profile::{ImportMode, Profile, ProfileChange, ProfileKind},
+    recovery::{read_or_recover, StartupError, StartupProblem},
+    setting_manager::{ModpackSetting, ModpackSettingManager, SettingManager, Settings},
+    watcher::SettingWatcher,
+};
 
 pub struct Setting {
     globle: SettingManager,
     settings: HashMap<i32, ModpackSettingManager>,
+    //id_setting.json 中登记的所有整合包, 包括文件夹已经不存在的
+    instances: Vec<SettingPath>,
+    //下一个整合包的 id, 只增加, 和登记一起保存
+    next_id: i32,
+    registry_path: PathBuf,
+    problems: Vec<StartupProblem>,
+    //设置改变时通知前端
+    listener: Option<Listener>,
+    //notify 的 watcher 不保证是 Sync
+    watcher: Option<Mutex<SettingWatcher>>,
 }
 
+static INSTANCE: OnceLock<RwLock<Setting>> = OnceLock::new();
+//启动失败的原因, 此时设置不可用, 前端通过 get_startup_problems 显示
+static STARTUP_ERROR: OnceLock<StartupError> = OnceLock::new();
+
 impl Setting {
-    pub fn instance() -> &'static RwLock<Setting> {
-        static INSTANCE: OnceLock<RwLock<Setting>> = OnceLock::new();
-        INSTANCE.get_or_init(|| RwLock::new(Self::create()))
+    //启动时调用一次, 之后通过 instance 获取
+    pub fn init() -> std::result::Result<&'static RwLock<Setting>, StartupError> {
+        if let Some(setting) = INSTANCE.get() {
+            return Ok(setting);
+        }
+        match Self::create() {
+            Ok(setting) => Ok(INSTANCE.get_or_init(|| RwLock::new(setting))),
+            Err(err) => {
+                let _ = STARTUP_ERROR.set(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    //启动失败时返回 Unavailable, 不会 panic
+    pub fn instance() -> std::result::Result<&'static RwLock<Setting>, SettingError> {
+        INSTANCE.get().ok_or_else(|| SettingError::Unavailable {
+            message: STARTUP_ERROR
+                .get()
+                .map(|e| e.to_string())
+                .unwrap_or_else(|| "Setting is not initialized".to_string()),
+        })
+    }
+
+    //启动时的问题, 启动失败时只有失败的原因
+    pub fn startup_problems() -> Vec<StartupProblem> {
+        match (INSTANCE.get(), STARTUP_ERROR.get()) {
+            (Some(setting), _) => setting.read().unwrap().problems().to_vec(),
+            (None, Some(err)) => vec![StartupProblem::from(err)],
+            (None, None) => Vec::new(),
+        }
     }
 
     pub fn get_globle(&self) -> &SettingManager {
@@ -41,60 +91,640 @@ impl Setting {
         self.settings.get_mut(&id)
     }
 
-    pub fn create() -> Self {
-        let file_path = dirs::get_config_dirs().unwrap().join("id_setting.json");
-        let mut file;
-        if file_path.exists() {
-            file = File::open(file_path).expect("Failed to open setting file");
-        } else {
-            file = File::create(&file_path).expect("Failed to create setting file");
-            //先写入一个空的
-            let setting: Vec<SettingPath> = Vec::new();
-            let content = serde_json::to_string(&setting).expect("Failed to serialize setting");
-            file.write_all(content.as_bytes())
-                .expect("Failed write setting_id.json");
-            file = File::open(&file_path).expect("Failed to create setting file");
-        }
-
-        let mut content = String::new();
-        file.read_to_string(&mut content)
-            .expect("Failed to read setting file");
-        let mut setting: Vec<SettingPath> =
-            serde_json::from_str(&content).expect("Failed to parse setting file");
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.listener = Some(listener);
+    }
+
+    //监听全局设置和所有整合包设置所在的目录
+    pub fn set_watcher(&mut self, mut watcher: SettingWatcher) -> notify::Result<()> {
+        if let Some(config_dir) = self.globle.get_setting_file_path().parent() {
+            watcher.watch_dir(config_dir)?;
+        }
+        self.watcher = Some(Mutex::new(watcher));
+        let paths: Vec<_> = self
+            .instances
+            .iter()
+            .filter(|s| self.settings.contains_key(&s.id))
+            .map(|s| PathBuf::from(&s.modpack_path))
+            .collect();
+        for modpack_path in paths {
+            self.watch_instance(&modpack_path);
+        }
+        Ok(())
+    }
+
+    //监听失败只会让外部修改无法自动刷新, 不影响整合包本身
+    fn watch_instance(&mut self, modpack_path: &Path) {
+        if let Some(watcher) = &mut self.watcher {
+            let rev = modpack_path.join("rev");
+            if fs::create_dir_all(&rev).is_ok() {
+                let _ = watcher.get_mut().unwrap().watch_dir(&rev);
+            }
+        }
+    }
+
+    fn unwatch_instance(&mut self, modpack_path: &Path) {
+        if let Some(watcher) = &mut self.watcher {
+            let _ = watcher
+                .get_mut()
+                .unwrap()
+                .unwatch_dir(&modpack_path.join("rev"));
+        }
+    }
+
+    //启动时自动修复的问题
+    pub fn problems(&self) -> &[StartupProblem] {
+        &self.problems
+    }
+
+    //启动后出现的问题, 如无法监听设置文件
+    pub fn add_problem(&mut self, problem: StartupProblem) {
+        self.problems.push(problem);
+    }
+
+    pub fn create() -> std::result::Result<Self, StartupError> {
+        let config_dir = dirs::get_config_dirs().map_err(|e| StartupError::ConfigDir {
+            message: e.to_string(),
+        })?;
+        Self::load_dir(&config_dir)
+    }
+
+    //读取配置目录中的设置, 每个文件损坏时只隔离它自己
+    fn load_dir(config_dir: &Path) -> std::result::Result<Self, StartupError> {
+        let mut problems = Vec::new();
+        //旧版本单独保存的 java 列表, setting.json 中没有 java 时才会读取
+        //先检查它, 否则它损坏时会被当成 setting.json 损坏
+        let java_versions = config_dir.join("java_versions.json");
+        read_or_recover(
+            &java_versions,
+            |content| Ok(serde_json::from_str::<JavaVersions>(content)?),
+            &mut problems,
+        )
+        .map_err(|e| StartupError::io(&java_versions, e))?;
+        let globle = SettingManager::load(&config_dir.join("setting.json"), &mut problems)?;
+        let mut setting = Self::load_from(config_dir.join("id_setting.json"), globle)?;
+        problems.append(&mut setting.problems);
+        setting.problems = problems;
+        Ok(setting)
+    }
+
+    pub fn load_from(
+        file_path: PathBuf,
+        globle: SettingManager,
+    ) -> std::result::Result<Self, StartupError> {
+        let mut problems = Vec::new();
+        let registry = read_or_recover(&file_path, Registry::parse, &mut problems)
+            .map_err(|e| StartupError::io(&file_path, e))?;
+        let missing = registry.is_none();
+        let Registry {
+            next_id,
+            instances: setting,
+        } = registry.unwrap_or_default();
         let mut settings = HashMap::new();
 
-        for s in setting.iter_mut() {
-            let id = s.id;
-            if id == -1 {
+        for s in setting.iter() {
+            let modpack_path = PathBuf::from(&s.modpack_path);
+            //文件夹不存在时只保留登记, 在列表中标记为丢失
+            if !modpack_path.is_dir() {
                 continue;
             }
-            let modpack_path = PathBuf::from_str(s.modpack_path.as_str()).unwrap();
-            settings.insert(
-                id,
-                ModpackSettingManager::read(id, modpack_path)
-                    .expect("Failed to read setting manager"),
-            );
+            //单个整合包读取失败时跳过, 不影响其它整合包
+            match ModpackSettingManager::load(s.id, modpack_path, &mut problems) {
+                Ok(manager) => {
+                    settings.insert(s.id, manager);
+                }
+                Err(err) => problems
+                    .push(StartupProblem::new(Path::new(&s.modpack_path), err).with_instance(s.id)),
+            }
         }
 
-        Setting {
-            globle: SettingManager::read().unwrap(),
+        let setting = Setting {
+            globle,
             settings,
+            instances: setting,
+            next_id,
+            registry_path: file_path,
+            problems,
+            listener: None,
+            watcher: None,
+        };
+        //先写入一个空的
+        if missing {
+            setting
+                .save_instances()
+                .map_err(|e| StartupError::io(&setting.registry_path, e))?;
+        }
+        Ok(setting)
+    }
+
+    pub fn list_instances(&self) -> Vec<InstanceInfo> {
+        self.instances
+            .iter()
+            .map(|s| InstanceInfo::new(s, self.settings.contains_key(&s.id)))
+            .collect()
+    }
+
+    //在指定文件夹创建一个整合包, 文件夹不存在时会自动创建
+    pub fn create_instance(
+        &mut self,
+        name: String,
+        modpack_path: PathBuf,
+    ) -> Result<InstanceInfo, SettingError> {
+        if modpack_path.is_file() {
+            return Err(invalid_instance(format!(
+                "`{}` is a file",
+                modpack_path.display()
+            )));
+        }
+        //只有启动器创建的文件夹在删除整合包时才会整个删除
+        let created = fs::read_dir(&modpack_path)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(true);
+        fs::create_dir_all(&modpack_path).map_err(io_error)?;
+        self.check_unregistered(&modpack_path, None)?;
+
+        let id = self.next_id();
+        let manager = ModpackSettingManager::create(id, modpack_path.clone()).map_err(io_error)?;
+        manager.save().map_err(io_error)?;
+        let name = if name.trim().is_empty() {
+            default_name(&modpack_path)
+        } else {
+            name.trim().to_string()
+        };
+        let instance = SettingPath {
+            id,
+            modpack_path: modpack_path.to_string_lossy().to_string(),
+            name,
+            created,
+        };
+        let info = InstanceInfo::new(&instance, true);
+        self.instances.push(instance);
+        self.settings.insert(id, manager);
+        self.save_instances().map_err(io_error)?;
+        self.watch_instance(&modpack_path);
+        Ok(info)
+    }
+
+    pub fn rename_instance(&mut self, id: i32, name: String) -> Result<InstanceInfo, SettingError> {
+        if name.trim().is_empty() {
+            return Err(invalid_instance("Instance name can not be empty"));
+        }
+        let loaded = self.settings.contains_key(&id);
+        let instance = self.find_instance_mut(id)?;
+        instance.name = name.trim().to_string();
+        let info = InstanceInfo::new(instance, loaded);
+        self.save_instances().map_err(io_error)?;
+        Ok(info)
+    }
+
+    //文件夹被移动后重新指定位置
+    pub fn relocate_instance(
+        &mut self,
+        id: i32,
+        modpack_path: PathBuf,
+    ) -> Result<InstanceInfo, SettingError> {
+        if !modpack_path.is_dir() {
+            return Err(invalid_instance(format!(
+                "`{}` is not a directory",
+                modpack_path.display()
+            )));
+        }
+        self.check_unregistered(&modpack_path, Some(id))?;
+        let manager = ModpackSettingManager::read(id, modpack_path.clone())
+            .map_err(|e| invalid_instance(format!("{:#}", e)))?;
+        let instance = self.find_instance_mut(id)?;
+        let old_path = PathBuf::from(&instance.modpack_path);
+        instance.modpack_path = modpack_path.to_string_lossy().to_string();
+        //新的位置不一定是启动器创建的
+        instance.created = false;
+        let info = InstanceInfo::new(instance, true);
+        self.settings.insert(id, manager);
+        self.save_instances().map_err(io_error)?;
+        self.unwatch_instance(&old_path);
+        self.watch_instance(&modpack_path);
+        Ok(info)
+    }
+
+    //取消登记, delete_files 为 true 时同时删除整合包的文件
+    //只有启动器创建的文件夹会整个删除, 其它文件夹只删除其中的 rev 设置目录
+    pub fn remove_instance(&mut self, id: i32, delete_files: bool) -> Result<(), SettingError> {
+        let index = self
+            .instances
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or(SettingError::InstanceNotFound { id })?;
+        let modpack_path = PathBuf::from(&self.instances[index].modpack_path);
+        if delete_files {
+            let target = if self.instances[index].created {
+                modpack_path.clone()
+            } else {
+                modpack_path.join("rev")
+            };
+            if target.exists() {
+                self.unwatch_instance(&modpack_path);
+                fs::remove_dir_all(target).map_err(io_error)?;
+            }
+        }
+        self.instances.remove(index);
+        self.settings.remove(&id);
+        self.unwatch_instance(&modpack_path);
+        self.save_instances().map_err(io_error)
+    }
+
+    fn find_instance_mut(&mut self, id: i32) -> Result<&mut SettingPath, SettingError> {
+        self.instances
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or(SettingError::InstanceNotFound { id })
+    }
+
+    //文件夹已经被其它整合包登记时返回错误, except 为正在移动的整合包
+    fn check_unregistered(
+        &self,
+        modpack_path: &Path,
+        except: Option<i32>,
+    ) -> Result<(), SettingError> {
+        let canonical_path = fs::canonicalize(modpack_path).map_err(io_error)?;
+        if self
+            .instances
+            .iter()
+            .filter(|s| Some(s.id) != except)
+            .any(|s| {
+                fs::canonicalize(&s.modpack_path)
+                    .map(|p| p == canonical_path)
+                    .unwrap_or(false)
+            })
+        {
+            return Err(invalid_instance(format!(
+                "`{}` is already registered as an instance",
+                modpack_path.display()
+            )));
+        }
+        Ok(())
+    }
+
+    //删除的整合包的 id 也不会再分配, 计数保存在 id_setting.json 中
+    fn next_id(&mut self) -> i32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn save_instances(&self) -> Result<()> {
+        let registry = RegistryRef {
+            next_id: self.next_id,
+            instances: &self.instances,
+        };
+        let content = serde_json::to_string_pretty(&registry)?;
+        write_atomic(&self.registry_path, content.as_bytes())?;
+        Ok(())
+    }
+
+    //读取实际生效的值, 同时返回值的来源
+    pub fn get_value(&self, id: i32, name: String) -> Result<SettingValue, SettingError> {
+        let not_found = |_| SettingError::ItemNotFound { item: name.clone() };
+        let globle = self.globle.get_setting();
+        if id == -1 {
+            let value = globle.get(name.clone()).map_err(not_found)?;
+            return Ok(SettingValue {
+                value,
+                source: SettingSource::Global,
+            });
+        }
+        let setting_manager = self
+            .settings
+            .get(&id)
+            .ok_or(SettingError::InstanceNotFound { id })?;
+        let setting = setting_manager.get_setting();
+        let source = match setting.is_overridden(&name) {
+            Some(true) => SettingSource::Overridden,
+            Some(false) => SettingSource::Inherited,
+            None => return Err(SettingError::ItemNotFound { item: name }),
+        };
+        let value = setting.get(name.clone(), globle).map_err(not_found)?;
+        Ok(SettingValue { value, source })
+    }
+
+    //取消整合包对全局设置的覆盖, name 为 None 时取消所有覆盖
+    pub fn reset_override(&mut self, id: i32, name: Option<String>) -> Result<(), SettingError> {
+        let before = self.snapshots(id);
+        let result = self.reset_override_inner(id, name);
+        self.emit_changes(&before);
+        result
+    }
+
+    fn reset_override_inner(&mut self, id: i32, name: Option<String>) -> Result<(), SettingError> {
+        let setting_manager = self
+            .settings
+            .get_mut(&id)
+            .ok_or(SettingError::InstanceNotFound { id })?;
+        match name {
+            Some(name) => {
+                //分组名也可以重置, 此时没有 readonly 信息
+                if ModpackSetting::item_readonly(&name) == Some(true) {
+                    return Err(SettingError::Readonly { item: name });
+                }
+                setting_manager
+                    .get_setting_mut()
+                    .reset(&name)
+                    .map_err(|_| SettingError::ItemNotFound { item: name.clone() })?;
+            }
+            None => setting_manager.get_setting_mut().reset_all(),
         }
+        setting_manager.save().map_err(|e| SettingError::Io {
+            message: format!("{:#}", e),
+        })
     }
 
-    pub fn change(&mut self, id: i32, name: String, value: Vec<String>) -> Result<()> {
+    //修改设置并返回修改后的实际值, 所有受影响的设置项都会发送事件
+    pub fn change(&mut self, id: i32, name: String, value: Value) -> Result<Value, SettingError> {
+        let before = self.snapshots(id);
+        //保存失败时内存中的值已经改变, 同样需要通知
+        let result = self.change_inner(id, name, value);
+        self.emit_changes(&before);
+        result
+    }
+
+    fn change_inner(&mut self, id: i32, name: String, value: Value) -> Result<Value, SettingError> {
+        let invalid = |e: anyhow::Error| SettingError::InvalidValue {
+            item: name.clone(),
+            message: format!("{:#}", e),
+        };
+        let io = |e: anyhow::Error| SettingError::Io {
+            message: format!("{:#}", e),
+        };
         if id == -1 {
-            self.globle.get_setting_mut().change(name, value)?;
-            self.globle.save()?;
+            check_item(&name, Settings::item_readonly(&name))?;
+            let globle = self.globle.get_setting_mut();
+            globle.change(name.clone(), value).map_err(invalid)?;
+            self.globle.save().map_err(io)?;
+            self.globle.get_setting().get(name.clone()).map_err(io)
         } else if let Some(setting_manager) = self.settings.get_mut(&id) {
+            check_item(&name, ModpackSetting::item_readonly(&name))?;
+            let globle = self.globle.get_setting();
             setting_manager
                 .get_setting_mut()
-                .change(name, value, self.globle.get_setting())?;
-            setting_manager.save()?;
+                .change(name.clone(), value, globle)
+                .map_err(invalid)?;
+            setting_manager.save().map_err(io)?;
+            setting_manager
+                .get_setting()
+                .get(name.clone(), globle)
+                .map_err(io)
         } else {
-            return Err(anyhow::anyhow!("Setting manager not found for id: {}", id));
+            Err(SettingError::InstanceNotFound { id })
         }
-        Ok(())
+    }
+
+    //导出全局设置(id 为 -1)或整合包覆盖的设置到 path
+    pub fn export_profile(&self, id: i32, path: &Path) -> Result<(), SettingError> {
+        let io = |e: anyhow::Error| SettingError::Io {
+            message: format!("{:#}", e),
+        };
+        let profile = if id == -1 {
+            let settings = self.globle.get_setting().export().map_err(io)?;
+            Profile::new(ProfileKind::Global, Settings::SCHEMA_VERSION, settings)
+        } else {
+            let setting_manager = self
+                .settings
+                .get(&id)
+                .ok_or(SettingError::InstanceNotFound { id })?;
+            let settings = setting_manager.get_setting().export().map_err(io)?;
+            Profile::new(
+                ProfileKind::Instance,
+                ModpackSetting::SCHEMA_VERSION,
+                settings,
+            )
+        };
+        profile.write(path).map_err(io)
+    }
+
+    //导入前预览实际生效的值会怎样改变, 不修改当前设置
+    pub fn preview_import(
+        &self,
+        id: i32,
+        path: &Path,
+        mode: ImportMode,
+    ) -> Result<Vec<ProfileChange>, SettingError> {
+        let (imported, warnings) = self.imported(id, path, mode)?;
+        let after = match &imported {
+            Imported::Global(globle) => snapshot_of(globle, None),
+            Imported::Instance(setting) => snapshot_of(self.globle.get_setting(), Some(setting)),
+        };
+        let mut changes = profile_changes(&self.snapshot(id), &after);
+        changes.extend(warnings);
+        Ok(changes)
+    }
+
+    //导入配置文件并保存, 返回和预览相同的改变
+    pub fn import_profile(
+        &mut self,
+        id: i32,
+        path: &Path,
+        mode: ImportMode,
+    ) -> Result<Vec<ProfileChange>, SettingError> {
+        let (imported, warnings) = self.imported(id, path, mode)?;
+        let before = self.snapshots(id);
+        let result = match imported {
+            Imported::Global(globle) => {
+                *self.globle.get_setting_mut() = globle;
+                self.globle.save()
+            }
+            Imported::Instance(setting) => {
+                let setting_manager = self
+                    .settings
+                    .get_mut(&id)
+                    .ok_or(SettingError::InstanceNotFound { id })?;
+                *setting_manager.get_setting_mut() = setting;
+                setting_manager.save()
+            }
+        };
+        self.emit_changes(&before);
+        result.map_err(|e| SettingError::Io {
+            message: format!("{:#}", e),
+        })?;
+        let mut changes = profile_changes(&before[&id], &self.snapshot(id));
+        changes.extend(warnings);
+        Ok(changes)
+    }
+
+    //在当前设置的副本上导入, 出错时当前设置不受影响, 同时返回导入时的提示
+    fn imported(
+        &self,
+        id: i32,
+        path: &Path,
+        mode: ImportMode,
+    ) -> Result<(Imported, Vec<ProfileChange>), SettingError> {
+        let invalid = |e: anyhow::Error| SettingError::InvalidProfile {
+            message: format!("{:#}", e),
+        };
+        let profile = Profile::read(path).map_err(invalid)?;
+        //全局设置和整合包设置的 schema_version 各自独立
+        let mut settings = profile.versioned_settings();
+        match profile.kind {
+            ProfileKind::Global => Settings::migrate(&mut settings),
+            ProfileKind::Instance => ModpackSetting::migrate(&mut settings),
+        }
+        .map_err(invalid)?;
+        let replace = mode == ImportMode::Replace;
+        let globle = self.globle.get_setting();
+        if id == -1 {
+            let mut globle = globle.clone();
+            globle.import(&settings, replace).map_err(invalid)?;
+            let warnings = import_warnings(&settings, &snapshot_of(&globle, None));
+            Ok((Imported::Global(globle), warnings))
+        } else {
+            let setting_manager = self
+                .settings
+                .get(&id)
+                .ok_or(SettingError::InstanceNotFound { id })?;
+            let mut setting = setting_manager
+                .get_setting()
+                .save()
+                .and_then(ModpackSetting::read)
+                .map_err(invalid)?;
+            setting
+                .import(&settings, replace, globle)
+                .map_err(invalid)?;
+            let warnings = import_warnings(&settings, &snapshot_of(globle, Some(&setting)));
+            Ok((Imported::Instance(setting), warnings))
+        }
+    }
+
+    //设置文件被外部修改后重新读取, 返回发送的事件
+    //自己写入的文件内容和内存中一致, 不会产生事件
+    pub fn reload_files(&mut self, paths: &[PathBuf]) -> Vec<SettingEvent> {
+        let before = self.snapshots(-1);
+        let changed = |file: PathBuf| paths.iter().any(|path| same_file(path, &file));
+        if changed(self.globle.get_setting_file_path()) {
+            //文件内容不完整时保持当前设置, 等待下一次修改
+            let _ = self.globle.reload();
+        }
+        for setting_manager in self.settings.values_mut() {
+            if changed(setting_manager.get_setting_file_path()) {
+                let _ = setting_manager.reload();
+            }
+        }
+        self.emit_changes(&before)
+    }
+
+    //修改 id 对应设置前需要记录的值, 全局设置会影响所有整合包
+    fn snapshots(&self, id: i32) -> HashMap<i32, Snapshot> {
+        let mut ids = vec![id];
+        if id == -1 {
+            ids.extend(self.settings.keys());
+        }
+        ids.into_iter().map(|id| (id, self.snapshot(id))).collect()
+    }
+
+    fn snapshot(&self, id: i32) -> Snapshot {
+        let setting = self.settings.get(&id).map(|m| m.get_setting());
+        snapshot_of(self.globle.get_setting(), setting)
+    }
+
+    fn emit_changes(&self, before: &HashMap<i32, Snapshot>) -> Vec<SettingEvent> {
+        let after: HashMap<_, _> = before.keys().map(|id| (*id, self.snapshot(*id))).collect();
+        let events = events::diff(before, &after);
+        if let Some(listener) = &self.listener {
+            for event in &events {
+                listener(event);
+            }
+        }
+        events
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingSource {
+    //全局设置
+    Global,
+    //整合包没有覆盖, 使用全局设置
+    Inherited,
+    //整合包覆盖了全局设置
+    Overridden,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SettingValue {
+    pub value: Value,
+    pub source: SettingSource,
+}
+
+//导入后还没有保存的设置
+enum Imported {
+    Global(Settings),
+    Instance(ModpackSetting),
+}
+
+//所有设置项实际生效的值, setting 为 None 时是全局设置
+fn snapshot_of(globle: &Settings, setting: Option<&ModpackSetting>) -> Snapshot {
+    Settings::items()
+        .into_iter()
+        .filter_map(|item| {
+            let value = match setting {
+                //整合包不能覆盖的设置项使用全局设置
+                Some(setting) => setting
+                    .get(item.clone(), globle)
+                    .or_else(|_| globle.get(item.clone())),
+                None => globle.get(item.clone()),
+            };
+            value.ok().map(|value| (item, value))
+        })
+        .collect()
+}
+
+fn profile_changes(before: &Snapshot, after: &Snapshot) -> Vec<ProfileChange> {
+    after
+        .iter()
+        .filter(|(item, value)| before.get(*item) != Some(*value))
+        .map(|(item, value)| ProfileChange {
+            item: item.clone(),
+            before: before.get(item).cloned().unwrap_or(Value::Null),
+            after: value.clone(),
+            warning: None,
+        })
+        .collect()
+}
+
+//配置文件中的 java 在这台机器上找不到时保持当前的 java, 在改变列表中提示
+fn import_warnings(settings: &Value, after: &Snapshot) -> Vec<ProfileChange> {
+    let (Some(value), Some(java)) = (settings.get("java"), after.get("java")) else {
+        return Vec::new();
+    };
+    let Ok(imported) = serde_json::from_value::<JavaVersions>(java.clone()) else {
+        return Vec::new();
+    };
+    JavaVersions::import_warning(value, &imported)
+        .map(|warning| ProfileChange {
+            item: "java".to_string(),
+            before: java.clone(),
+            after: java.clone(),
+            warning: Some(warning),
+        })
+        .into_iter()
+        .collect()
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    if a == b {
+        return true;
+    }
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn check_item(name: &str, readonly: Option<bool>) -> Result<(), SettingError> {
+    match readonly {
+        None => Err(SettingError::ItemNotFound {
+            item: name.to_string(),
+        }),
+        Some(true) => Err(SettingError::Readonly {
+            item: name.to_string(),
+        }),
+        Some(false) => Ok(()),
     }
 }
 
@@ -102,4 +732,619 @@ impl Setting {
 struct SettingPath {
     id: i32,
     modpack_path: String,
+    #[serde(default)]
+    name: String,
+    //文件夹是否由启动器创建(创建时不存在或为空), 决定删除整合包时能否删除整个文件夹
+    #[serde(default)]
+    created: bool,
+}
+
+//id_setting.json 的内容, 旧版本只有整合包的数组
+#[derive(Default, Deserialize)]
+struct Registry {
+    next_id: i32,
+    instances: Vec<SettingPath>,
+}
+
+#[derive(Serialize)]
+struct RegistryRef<'a> {
+    next_id: i32,
+    instances: &'a [SettingPath],
+}
+
+impl Registry {
+    fn parse(content: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(content)?;
+        let mut registry = if value.is_array() {
+            Registry {
+                next_id: 0,
+                instances: serde_json::from_value(value)?,
+            }
+        } else {
+            serde_json::from_value(value)?
+        };
+        //-1 留给全局设置, 计数不能小于已经登记的 id
+        registry.instances.retain(|s| s.id != -1);
+        let used = registry.instances.iter().map(|s| s.id + 1).max();
+        registry.next_id = registry.next_id.max(used.unwrap_or(0)).max(0);
+        Ok(registry)
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InstanceStatus {
+    Ok,
+    //文件夹被删除或移动了
+    Missing,
+    //设置文件无法读取, 启动时被跳过
+    Broken,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct InstanceInfo {
+    pub id: i32,
+    pub name: String,
+    pub path: String,
+    pub status: InstanceStatus,
+}
+
+impl InstanceInfo {
+    fn new(instance: &SettingPath, loaded: bool) -> Self {
+        let path = Path::new(&instance.modpack_path);
+        let name = if instance.name.is_empty() {
+            default_name(path)
+        } else {
+            instance.name.clone()
+        };
+        InstanceInfo {
+            id: instance.id,
+            name,
+            path: instance.modpack_path.clone(),
+            status: if !path.is_dir() {
+                InstanceStatus::Missing
+            } else if loaded {
+                InstanceStatus::Ok
+            } else {
+                InstanceStatus::Broken
+            },
+        }
+    }
+}
+
+fn io_error(err: impl std::fmt::Display) -> SettingError {
+    SettingError::Io {
+        message: format!("{:#}", err),
+    }
+}
+
+fn invalid_instance(message: impl std::fmt::Display) -> SettingError {
+    SettingError::InvalidInstance {
+        message: message.to_string(),
+    }
+}
+
+//没有名字时使用文件夹名
+fn default_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::atomic_file::backup_path;
+
+    //先写入全局的 java 列表, 不会读取本机的配置目录或扫描本机的 java
+    fn load(dir: &Path) -> Setting {
+        let setting_json = dir.join("setting.json");
+        if !setting_json.exists() {
+            let content = serde_json::json!({
+                "schema_version": Settings::SCHEMA_VERSION,
+                "java": {
+                    "versions": [
+                        { "path": "/opt/java-17/bin/java", "version": "17.0.2" },
+                        { "path": "/opt/java-21/bin/java", "version": "21.0.1" },
+                    ],
+                    "select": 0,
+                },
+            });
+            fs::write(&setting_json, content.to_string()).unwrap();
+        }
+        Setting::load_dir(dir).unwrap()
+    }
+
+    #[test]
+    fn test_instance_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut setting = load(dir.path());
+        assert!(setting.list_instances().is_empty());
+
+        let a = setting
+            .create_instance("A".to_string(), dir.path().join("a"))
+            .unwrap();
+        let b = setting
+            .create_instance(" ".to_string(), dir.path().join("b"))
+            .unwrap();
+        assert_ne!(a.id, b.id);
+        assert_eq!(b.name, "b");
+        assert!(dir
+            .path()
+            .join("a")
+            .join("rev")
+            .join("settings.json")
+            .exists());
+        //同一个文件夹不能登记两次
+        assert!(setting
+            .create_instance("A2".to_string(), dir.path().join("a"))
+            .is_err());
+
+        setting
+            .rename_instance(a.id, "Renamed".to_string())
+            .unwrap();
+        assert!(setting.rename_instance(a.id, "".to_string()).is_err());
+        assert!(setting.rename_instance(100, "x".to_string()).is_err());
+
+        //重新读取 id_setting.json
+        let mut setting = load(dir.path());
+        let instances = setting.list_instances();
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0].name, "Renamed");
+        assert!(setting.get(a.id).is_some());
+
+        setting.remove_instance(b.id, true).unwrap();
+        assert!(!dir.path().join("b").exists());
+        setting.remove_instance(a.id, false).unwrap();
+        assert!(dir.path().join("a").exists());
+        assert!(matches!(
+            setting.remove_instance(a.id, false),
+            Err(SettingError::InstanceNotFound { .. })
+        ));
+        assert!(load(dir.path()).list_instances().is_empty());
+    }
+
+    #[test]
+    fn test_remove_existing_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut setting = load(dir.path());
+        //登记用户已有的文件夹
+        let user_dir = dir.path().join("user");
+        fs::create_dir_all(&user_dir).unwrap();
+        fs::write(user_dir.join("options.txt"), "keep").unwrap();
+        let user = setting
+            .create_instance("User".to_string(), user_dir.clone())
+            .unwrap();
+
+        //重新读取后仍然记得文件夹不是启动器创建的
+        let mut setting = load(dir.path());
+        setting.remove_instance(user.id, true).unwrap();
+        assert!(user_dir.join("options.txt").exists());
+        assert!(!user_dir.join("rev").exists());
+    }
+
+    #[test]
+    fn test_missing_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut setting = load(dir.path());
+        let a = setting
+            .create_instance("A".to_string(), dir.path().join("a"))
+            .unwrap();
+        fs::rename(dir.path().join("a"), dir.path().join("moved")).unwrap();
+
+        let mut setting = load(dir.path());
+        let instances = setting.list_instances();
+        assert_eq!(instances[0].status, InstanceStatus::Missing);
+        assert!(setting.get(a.id).is_none());
+        assert!(matches!(
+            setting.change(a.id, "java".to_string(), Value::Null),
+            Err(SettingError::InstanceNotFound { .. })
+        ));
+
+        assert!(setting
+            .relocate_instance(a.id, dir.path().join("other"))
+            .is_err());
+        setting
+            .relocate_instance(a.id, dir.path().join("moved"))
+            .unwrap();
+        assert_eq!(setting.list_instances()[0].status, InstanceStatus::Ok);
+        assert!(setting.get(a.id).is_some());
+
+        //被删除的最大 id 之后再分配
+        let b = setting
+            .create_instance("B".to_string(), dir.path().join("b"))
+            .unwrap();
+        assert_eq!(b.id, a.id + 1);
+
+        //不能移动到其它整合包登记的文件夹
+        assert!(setting
+            .relocate_instance(a.id, dir.path().join("b"))
+            .is_err());
+        assert_eq!(setting.list_instances()[0].status, InstanceStatus::Ok);
+        assert!(setting.get(b.id).is_some());
+        setting
+            .relocate_instance(a.id, dir.path().join("moved"))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_instance_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut setting = load(dir.path());
+        let a = setting
+            .create_instance("A".to_string(), dir.path().join("a"))
+            .unwrap();
+        let b = setting
+            .create_instance("B".to_string(), dir.path().join("b"))
+            .unwrap();
+
+        //删除最大的 id 后重新读取, 也不会再分配这个 id
+        setting.remove_instance(b.id, false).unwrap();
+        let mut setting = load(dir.path());
+        let c = setting
+            .create_instance("C".to_string(), dir.path().join("c"))
+            .unwrap();
+        assert_eq!(c.id, b.id + 1);
+
+        //旧版本的登记文件只有数组
+        let legacy = serde_json::json!([
+            { "id": a.id, "modpack_path": dir.path().join("a"), "name": "A" },
+            { "id": -1, "modpack_path": dir.path().join("b") },
+        ]);
+        fs::write(dir.path().join("id_setting.json"), legacy.to_string()).unwrap();
+        let mut setting = load(dir.path());
+        assert_eq!(setting.list_instances().len(), 1);
+        let d = setting
+            .create_instance("D".to_string(), dir.path().join("d"))
+            .unwrap();
+        assert_eq!(d.id, a.id + 1);
+    }
+
+    #[test]
+    fn test_corrupt_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut setting = load(dir.path());
+        let a = setting
+            .create_instance("A".to_string(), dir.path().join("a"))
+            .unwrap();
+        let b = setting
+            .create_instance("B".to_string(), dir.path().join("b"))
+            .unwrap();
+        let a_settings = dir.path().join("a").join("rev").join("settings.json");
+        fs::write(&a_settings, "{ not json").unwrap();
+
+        //损坏的整合包设置被隔离, 使用默认值继续加载
+        let setting = load(dir.path());
+        assert_eq!(setting.problems().len(), 1);
+        let problem = &setting.problems()[0];
+        assert_eq!(problem.instance, Some(a.id));
+        assert!(Path::new(problem.quarantined.as_ref().unwrap()).exists());
+        assert!(!a_settings.exists());
+        assert!(setting.get(a.id).is_some());
+        assert!(setting.get(b.id).is_some());
+
+        //整合包设置无法读取时跳过该整合包
+        fs::create_dir_all(&a_settings).unwrap();
+        let setting = load(dir.path());
+        assert_eq!(setting.problems().len(), 1);
+        assert!(setting.get(a.id).is_none());
+        assert!(setting.get(b.id).is_some());
+        assert_eq!(setting.list_instances()[0].status, InstanceStatus::Broken);
+        fs::remove_dir(&a_settings).unwrap();
+
+        //登记文件损坏时隔离并重新创建
+        fs::write(dir.path().join("id_setting.json"), "[{").unwrap();
+        let setting = load(dir.path());
+        assert!(setting.list_instances().is_empty());
+        assert_eq!(setting.problems().len(), 1);
+        assert_eq!(setting.problems()[0].instance, None);
+        let registry: Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join("id_setting.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            registry,
+            serde_json::json!({ "next_id": 0, "instances": [] })
+        );
+    }
+
+    #[test]
+    fn test_corrupt_java_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let setting_json = serde_json::json!({
+            "schema_version": Settings::SCHEMA_VERSION,
+            "java": { "versions": [], "select": 0 },
+        })
+        .to_string();
+        fs::write(dir.path().join("setting.json"), &setting_json).unwrap();
+        fs::write(dir.path().join("java_versions.json"), "{ not json").unwrap();
+
+        //只隔离损坏的 java_versions.json, setting.json 不受影响
+        let setting = Setting::load_dir(dir.path()).unwrap();
+        assert_eq!(setting.problems().len(), 1);
+        assert!(setting.problems()[0].path.ends_with("java_versions.json"));
+        assert!(!setting.problems()[0].fatal);
+        assert!(!dir.path().join("java_versions.json").exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("setting.json")).unwrap(),
+            setting_json
+        );
+    }
+
+    #[test]
+    fn test_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut setting = load(dir.path());
+        let a = setting
+            .create_instance("A".to_string(), dir.path().join("a"))
+            .unwrap();
+
+        assert!(matches!(
+            setting.change(a.id, "unknown".to_string(), Value::Null),
+            Err(SettingError::ItemNotFound { .. })
+        ));
+        assert!(matches!(
+            setting.change(
+                a.id,
+                "java".to_string(),
+                serde_json::json!({ "command": "select", "index": "x" })
+            ),
+            Err(SettingError::InvalidValue { .. })
+        ));
+        //修改失败时不会创建覆盖
+        assert!(setting.get(a.id).unwrap().get_setting().save().unwrap()["java"].is_null());
+
+        let value = setting
+            .change(
+                a.id,
+                "java".to_string(),
+                serde_json::json!({ "command": "select", "index": 1 }),
+            )
+            .unwrap();
+        assert_eq!(value["select"], 1);
+        assert!(setting.get(a.id).unwrap().get_setting().save().unwrap()["java"].is_object());
+    }
+
+    #[test]
+    fn test_reset_override() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut setting = load(dir.path());
+        let a = setting
+            .create_instance("A".to_string(), dir.path().join("a"))
+            .unwrap();
+
+        let value = setting.get_value(-1, "java".to_string()).unwrap();
+        assert_eq!(value.source, SettingSource::Global);
+        let value = setting.get_value(a.id, "java".to_string()).unwrap();
+        assert_eq!(value.source, SettingSource::Inherited);
+        assert!(matches!(
+            setting.get_value(a.id, "unknown".to_string()),
+            Err(SettingError::ItemNotFound { .. })
+        ));
+        assert!(matches!(
+            setting.get_value(100, "java".to_string()),
+            Err(SettingError::InstanceNotFound { .. })
+        ));
+
+        setting
+            .change(
+                a.id,
+                "java".to_string(),
+                serde_json::json!({ "command": "select", "index": 1 }),
+            )
+            .unwrap();
+        let value = setting.get_value(a.id, "java".to_string()).unwrap();
+        assert_eq!(value.source, SettingSource::Overridden);
+
+        setting
+            .reset_override(a.id, Some("java".to_string()))
+            .unwrap();
+        let value = setting.get_value(a.id, "java".to_string()).unwrap();
+        assert_eq!(value.source, SettingSource::Inherited);
+        assert!(matches!(
+            setting.reset_override(a.id, Some("unknown".to_string())),
+            Err(SettingError::ItemNotFound { .. })
+        ));
+
+        setting
+            .change(
+                a.id,
+                "java".to_string(),
+                serde_json::json!({ "command": "select", "index": 1 }),
+            )
+            .unwrap();
+        setting.reset_override(a.id, None).unwrap();
+        //重置结果已经保存
+        let setting = load(dir.path());
+        let value = setting.get_value(a.id, "java".to_string()).unwrap();
+        assert_eq!(value.source, SettingSource::Inherited);
+    }
+
+    #[test]
+    fn test_change_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut setting = load(dir.path());
+        let a = setting
+            .create_instance("A".to_string(), dir.path().join("a"))
+            .unwrap();
+        let received = std::sync::Arc::new(Mutex::new(Vec::new()));
+        {
+            let received = received.clone();
+            setting.set_listener(Box::new(move |event| {
+                received.lock().unwrap().push(event.clone())
+            }));
+        }
+        //修改失败时没有事件
+        setting
+            .change(
+                -1,
+                "java".to_string(),
+                serde_json::json!({ "command": "add", "path": "/not/java" }),
+            )
+            .unwrap_err();
+        assert!(received.lock().unwrap().is_empty());
+
+        //手动修改全局设置, 继承它的整合包也会收到事件
+        let file = setting.globle.get_setting_file_path();
+        setting.globle.save().unwrap();
+        let mut content: Value = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+        content["java"] = serde_json::json!({
+            "versions": [{ "path": "a", "version": "17" }],
+            "select": 0,
+        });
+        fs::write(&file, serde_json::to_string_pretty(&content).unwrap()).unwrap();
+        let events = setting.reload_files(std::slice::from_ref(&file));
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].id, -1);
+        assert_eq!(events[1].id, a.id);
+        assert_eq!(events[1].value["versions"][0]["path"], "a");
+        assert_eq!(*received.lock().unwrap(), events);
+
+        //自己写入的文件不会产生事件
+        setting.globle.save().unwrap();
+        assert!(setting.reload_files(std::slice::from_ref(&file)).is_empty());
+        //不完整的文件保持当前设置
+        fs::write(&file, "{").unwrap();
+        assert!(setting.reload_files(&[file]).is_empty());
+
+        received.lock().unwrap().clear();
+        setting
+            .change(
+                a.id,
+                "java".to_string(),
+                serde_json::json!({ "command": "remove", "index": 0 }),
+            )
+            .unwrap();
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].id, a.id);
+        assert_eq!(received[0].value["versions"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut setting = load(dir.path());
+        *setting.globle.get_setting_mut() = Settings::read(serde_json::json!({
+            "java": {
+                "versions": [
+                    { "path": "/a/java", "version": "17", "vendor": "Temurin" },
+                    { "path": "/b/java", "version": "21" },
+                ],
+                "select": 1,
+            }
+        }))
+        .unwrap();
+        let a = setting
+            .create_instance("A".to_string(), dir.path().join("a"))
+            .unwrap();
+
+        //导出的内容不包含路径, 没有覆盖的整合包导出为空
+        let globle_path = dir.path().join("globle.json");
+        setting.export_profile(-1, &globle_path).unwrap();
+        setting.export_profile(-1, &globle_path).unwrap();
+        //导出的文件不留备份
+        assert!(!backup_path(&globle_path).exists());
+        let profile = Profile::read(&globle_path).unwrap();
+        assert_eq!(profile.kind, ProfileKind::Global);
+        assert_eq!(
+            profile.settings,
+            serde_json::json!({ "java": { "version": "21", "vendor": null } })
+        );
+        let instance_path = dir.path().join("instance.json");
+        setting.export_profile(a.id, &instance_path).unwrap();
+        assert_eq!(
+            Profile::read(&instance_path).unwrap().settings,
+            serde_json::json!({})
+        );
+
+        //其它机器导出的配置文件, 按大版本和厂商重新选择 java
+        let other_path = dir.path().join("other.json");
+        Profile::new(
+            ProfileKind::Global,
+            Settings::SCHEMA_VERSION,
+            serde_json::json!({ "java": { "version": "17.0.2", "vendor": "Temurin" } }),
+        )
+        .write(&other_path)
+        .unwrap();
+        let changes = setting
+            .preview_import(a.id, &other_path, ImportMode::Merge)
+            .unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].item, "java");
+        assert_eq!(changes[0].before["select"], 1);
+        assert_eq!(changes[0].after["select"], 0);
+        let value = setting.get_value(a.id, "java".to_string()).unwrap();
+        assert_eq!(value.source, SettingSource::Inherited);
+
+        assert_eq!(
+            setting
+                .import_profile(a.id, &other_path, ImportMode::Merge)
+                .unwrap(),
+            changes
+        );
+        let value = setting.get_value(a.id, "java".to_string()).unwrap();
+        assert_eq!(value.source, SettingSource::Overridden);
+        assert_eq!(value.value["versions"][0]["path"], "/a/java");
+        //已经保存
+        let reloaded = load(dir.path());
+        let value = reloaded.get_value(a.id, "java".to_string()).unwrap();
+        assert_eq!(value.source, SettingSource::Overridden);
+
+        //合并空的配置文件没有改变, 替换时取消覆盖
+        assert!(setting
+            .preview_import(a.id, &instance_path, ImportMode::Merge)
+            .unwrap()
+            .is_empty());
+        setting
+            .import_profile(a.id, &instance_path, ImportMode::Replace)
+            .unwrap();
+        let value = setting.get_value(a.id, "java".to_string()).unwrap();
+        assert_eq!(value.source, SettingSource::Inherited);
+        assert_eq!(value.value["select"], 1);
+
+        //没有满足要求的 java 时保持当前的 java, 其它设置照常导入
+        Profile::new(
+            ProfileKind::Global,
+            Settings::SCHEMA_VERSION,
+            serde_json::json!({ "java": { "version": "25" } }),
+        )
+        .write(&other_path)
+        .unwrap();
+        let changes = setting
+            .preview_import(-1, &other_path, ImportMode::Merge)
+            .unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].item, "java");
+        assert_eq!(changes[0].before, changes[0].after);
+        assert!(changes[0].warning.as_deref().unwrap().contains("Java 25"));
+        assert_eq!(
+            setting
+                .import_profile(-1, &other_path, ImportMode::Merge)
+                .unwrap(),
+            changes
+        );
+        fs::write(&other_path, "{}").unwrap();
+        assert!(matches!(
+            setting.preview_import(-1, &other_path, ImportMode::Merge),
+            Err(SettingError::InvalidProfile { .. })
+        ));
+        let value = setting.get_value(-1, "java".to_string()).unwrap();
+        assert_eq!(value.value["select"], 1);
+
+        //替换时配置文件中没有 java, 保持当前的 java 列表, 不会读取本机的配置
+        Profile::new(
+            ProfileKind::Global,
+            Settings::SCHEMA_VERSION,
+            serde_json::json!({}),
+        )
+        .write(&other_path)
+        .unwrap();
+        assert!(setting
+            .import_profile(-1, &other_path, ImportMode::Replace)
+            .unwrap()
+            .is_empty());
+        let value = setting.get_value(-1, "java".to_string()).unwrap();
+        assert_eq!(value.value["versions"][0]["path"], "/a/java");
+        assert_eq!(value.value["select"], 1);
+    }
 }
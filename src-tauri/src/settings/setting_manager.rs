@@ -1,56 +1,80 @@
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value};
-use std::{
-    io::{Read, Write},
-    path::PathBuf,
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use super::{
+    atomic_file::write_atomic,
+    java_versions::JavaVersions,
+    migration::{save_migrated, Migration, Preserved},
+    recovery::{read_or_recover, StartupError, StartupProblem},
+    setting_trait::SettingTrait,
 };
 
-use crate::api::dirs;
-
-use super::{java_versions::JavaVersions, setting::Setting, setting_trait::SettingTrait};
-
 pub struct SettingManager {
     //-1 is global
     setting: Settings,
+    filepath: PathBuf,
+    preserved: Preserved,
 }
 
 impl SettingManager {
-    pub fn read() -> Result<Self> {
-        let filepath = dirs::get_config_dirs()?.join("setting.json");
-        let setting: Settings;
-        if filepath.exists() {
-            let mut file = std::fs::File::open(filepath)?;
-            let mut content = String::new();
-            file.read_to_string(&mut content)?;
-            setting = Settings::read(serde_json::from_str(&content)?)?;
-        } else {
-            setting = Settings::create()?;
-        }
-        Ok(SettingManager { setting })
+    //启动时读取, 文件损坏时隔离并使用默认设置
+    pub fn load(
+        filepath: &Path,
+        problems: &mut Vec<StartupProblem>,
+    ) -> std::result::Result<Self, StartupError> {
+        let loaded = read_or_recover(filepath, Settings::parse, problems)
+            .map_err(|e| StartupError::io(filepath, e))?;
+        Self::from_parsed(filepath.to_path_buf(), loaded).map_err(|e| StartupError::io(filepath, e))
     }
 
-    pub fn create() -> Result<Self> {
-        let setting = Settings::create()?;
-        Ok(SettingManager { setting })
+    //文件不存在或损坏时使用默认设置
+    //读取成功时不创建默认设置, 创建默认的 java 列表需要扫描本机
+    fn from_parsed(
+        filepath: PathBuf,
+        parsed: Option<(Settings, Preserved, Option<u32>)>,
+    ) -> Result<Self> {
+        let (setting, preserved, migrated_from) = match parsed {
+            Some(parsed) => parsed,
+            None => (Settings::create()?, Preserved::default(), None),
+        };
+        let manager = SettingManager {
+            setting,
+            filepath,
+            preserved,
+        };
+        save_migrated(&manager.filepath, migrated_from, || manager.save())?;
+        Ok(manager)
     }
 
     pub fn get_setting_file_path(&self) -> PathBuf {
-        dirs::get_config_dirs().unwrap()
+        self.filepath.clone()
     }
 
     pub fn save(&self) -> Result<()> {
-        let settings_value = self.setting.save()?;
-        let filepath = self.get_setting_file_path();
-        if !filepath.exists() {
-            std::fs::create_dir_all(filepath.parent().unwrap())?;
-        }
-        let mut file = std::fs::File::create(filepath)?;
-        let content = serde_json::to_string_pretty(&settings_value)?;
-        file.write_all(content.as_bytes())?;
+        write_atomic(&self.get_setting_file_path(), self.content()?.as_bytes())?;
         Ok(())
     }
 
+    //写入文件的内容
+    fn content(&self) -> Result<String> {
+        let mut settings_value = self.setting.save()?;
+        self.preserved.apply(&mut settings_value);
+        Ok(serde_json::to_string_pretty(&settings_value)?)
+    }
+
+    //文件被外部修改后重新读取, 内容和内存中一致时(包括自己刚写入的)返回 false
+    pub fn reload(&mut self) -> Result<bool> {
+        let content = std::fs::read_to_string(self.get_setting_file_path())?;
+        if content == self.content()? {
+            return Ok(false);
+        }
+        let (setting, preserved, _) = Settings::parse(&content)?;
+        self.setting = setting;
+        self.preserved = preserved;
+        Ok(true)
+    }
+
     pub fn get_setting(&self) -> &Settings {
         &self.setting
     }
@@ -61,44 +85,77 @@ impl SettingManager {
 }
 
 #[derive(Clone, setting_derive::Setting)]
+#[setting(migrations = SETTINGS_MIGRATIONS)]
 pub struct Settings {
     java: JavaVersions,
 }
 
-impl Settings {}
+//新增字段不需要迁移, 只有改名或改变格式时才在这里追加
+const SETTINGS_MIGRATIONS: &[Migration] = &[];
+
+impl Settings {
+    //全局设置的描述, 同时标记整合包可以覆盖的设置项
+    pub fn describe() -> Result<Value> {
+        Self::schema(&ModpackSetting::items())
+    }
+}
 
 pub struct ModpackSettingManager {
     id: i32,
     modpack_path: PathBuf,
     setting: ModpackSetting,
+    preserved: Preserved,
 }
 
 impl ModpackSettingManager {
     pub fn read(id: i32, modpack_path: PathBuf) -> Result<Self> {
         let filepath = modpack_path.join("rev").join("settings.json");
-        let setting: ModpackSetting;
-        if filepath.exists() {
-            let mut file = std::fs::File::open(filepath)?;
-            let mut content = String::new();
-            file.read_to_string(&mut content)?;
-            setting = ModpackSetting::read(serde_json::from_str(&content)?)?;
+        let parsed = if filepath.exists() {
+            Some(ModpackSetting::parse(&std::fs::read_to_string(&filepath)?)?)
         } else {
-            setting = ModpackSetting::create()?;
-        }
-        Ok(ModpackSettingManager {
-            id,
-            modpack_path,
-            setting,
-        })
+            None
+        };
+        Self::from_parsed(id, modpack_path, parsed)
+    }
+
+    //启动时读取, 文件损坏时隔离并使用默认设置
+    pub fn load(
+        id: i32,
+        modpack_path: PathBuf,
+        problems: &mut Vec<StartupProblem>,
+    ) -> std::result::Result<Self, StartupError> {
+        let filepath = modpack_path.join("rev").join("settings.json");
+        let mut found = Vec::new();
+        let loaded = read_or_recover(&filepath, ModpackSetting::parse, &mut found)
+            .map_err(|e| StartupError::io(&filepath, e))?;
+        problems.extend(found.into_iter().map(|p| p.with_instance(id)));
+        Self::from_parsed(id, modpack_path, loaded).map_err(|e| StartupError::io(&filepath, e))
     }
 
     pub fn create(id: i32, modpack_path: PathBuf) -> Result<Self> {
-        let setting = ModpackSetting::create()?;
-        Ok(ModpackSettingManager {
+        Self::from_parsed(id, modpack_path, None)
+    }
+
+    //文件不存在或损坏时使用默认设置
+    fn from_parsed(
+        id: i32,
+        modpack_path: PathBuf,
+        parsed: Option<(ModpackSetting, Preserved, Option<u32>)>,
+    ) -> Result<Self> {
+        let (setting, preserved, migrated_from) = match parsed {
+            Some(parsed) => parsed,
+            None => (ModpackSetting::create()?, Preserved::default(), None),
+        };
+        let manager = ModpackSettingManager {
             id,
             modpack_path,
             setting,
-        })
+            preserved,
+        };
+        save_migrated(&manager.get_setting_file_path(), migrated_from, || {
+            manager.save()
+        })?;
+        Ok(manager)
     }
 
     pub fn get_setting_file_path(&self) -> PathBuf {
@@ -106,17 +163,29 @@ impl ModpackSettingManager {
     }
 
     pub fn save(&self) -> Result<()> {
-        let settings_value = self.setting.save()?;
-        let filepath = self.get_setting_file_path();
-        if !filepath.exists() {
-            std::fs::create_dir_all(filepath.parent().unwrap())?;
-        }
-        let mut file = std::fs::File::create(filepath)?;
-        let content = serde_json::to_string_pretty(&settings_value)?;
-        file.write_all(content.as_bytes())?;
+        write_atomic(&self.get_setting_file_path(), self.content()?.as_bytes())?;
         Ok(())
     }
 
+    //写入文件的内容
+    fn content(&self) -> Result<String> {
+        let mut settings_value = self.setting.save()?;
+        self.preserved.apply(&mut settings_value);
+        Ok(serde_json::to_string_pretty(&settings_value)?)
+    }
+
+    //文件被外部修改后重新读取, 内容和内存中一致时(包括自己刚写入的)返回 false
+    pub fn reload(&mut self) -> Result<bool> {
+        let content = std::fs::read_to_string(self.get_setting_file_path())?;
+        if content == self.content()? {
+            return Ok(false);
+        }
+        let (setting, preserved, _) = ModpackSetting::parse(&content)?;
+        self.setting = setting;
+        self.preserved = preserved;
+        Ok(true)
+    }
+
     pub fn get_setting(&self) -> &ModpackSetting {
         &self.setting
     }
@@ -127,8 +196,27 @@ impl ModpackSettingManager {
 }
 
 #[derive(setting_derive::ModpackSetting)]
+#[setting(migrations = MODPACK_SETTING_MIGRATIONS)]
 pub struct ModpackSetting {
     java: Option<JavaVersions>,
 }
 
-impl ModpackSetting {}
+const MODPACK_SETTING_MIGRATIONS: &[Migration] = &[];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        let schema = Settings::describe().unwrap();
+        assert_eq!(schema["schema_version"], Settings::SCHEMA_VERSION);
+        let java = &schema["items"][0];
+        assert_eq!(java["name"], "java");
+        assert_eq!(java["type"], "object");
+        assert_eq!(java["label"], "setting.java.label");
+        assert_eq!(java["overridable"], true);
+        assert_eq!(java["readonly"], false);
+        assert!(java["constraints"]["properties"]["versions"].is_object());
+    }
+}
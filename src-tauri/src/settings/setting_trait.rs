@@ -9,8 +9,26 @@ pub trait SettingTrait: Sized {
     fn write(&self) -> Result<Value>;
     //发送给tauri (name, value)
     fn send(&self) -> Result<Value>;
-    //接收来自tauri的值
-    fn receive(&mut self, value: Vec<String>) -> Result<()>;
+    //接收来自tauri的值, 类型不对时返回错误且不修改
+    fn receive(&mut self, value: Value) -> Result<()>;
+    //描述值的类型和约束, 类似 JSON Schema, 前端据此生成设置界面
+    //`type` 以外的字段作为约束, `default` 作为默认值
+    fn schema() -> Value {
+        serde_json::json!({ "type": "object" })
+    }
+    //导出到配置文件, 和机器相关的值(如路径)不应该导出
+    fn export(&self) -> Result<Value> {
+        self.write()
+    }
+    //从配置文件导入, current 为当前的值, 用来重新解析和机器相关的值
+    fn import(value: Value, current: Option<&Self>) -> Result<Self> {
+        let _ = current;
+        Self::read(Some(value))
+    }
+    //和机器相关的值(如 java 路径), 替换导入时配置文件中没有也保持当前值, 不恢复默认值
+    fn machine_specific() -> bool {
+        false
+    }
 
     fn read_modpack(json: Option<Value>) -> Result<Option<Self>> {
         match json {
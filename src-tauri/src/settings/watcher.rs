@@ -0,0 +1,117 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+//编辑器保存时通常会连续产生多个事件
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+//监听设置文件所在的目录, 原子写入会替换文件, 直接监听文件会失效
+pub struct SettingWatcher {
+    watcher: RecommendedWatcher,
+    watched: HashSet<PathBuf>,
+}
+
+impl SettingWatcher {
+    //on_change 在后台线程中调用, 参数为防抖后改变的文件
+    pub fn start(on_change: impl Fn(Vec<PathBuf>) + Send + 'static) -> notify::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                for path in event.paths {
+                    let _ = sender.send(path);
+                }
+            }
+        })?;
+        thread::spawn(move || debounce(receiver, DEBOUNCE, on_change));
+        Ok(SettingWatcher {
+            watcher,
+            watched: HashSet::new(),
+        })
+    }
+
+    pub fn watch_dir(&mut self, dir: &Path) -> notify::Result<()> {
+        if self.watched.contains(dir) {
+            return Ok(());
+        }
+        self.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        self.watched.insert(dir.to_path_buf());
+        Ok(())
+    }
+
+    pub fn unwatch_dir(&mut self, dir: &Path) -> notify::Result<()> {
+        if self.watched.remove(dir) {
+            self.watcher.unwatch(dir)?;
+        }
+        Ok(())
+    }
+}
+
+//收到事件后等待 delay 内没有新事件再一起处理, 发送端关闭时处理剩余的事件并退出
+pub fn debounce(receiver: Receiver<PathBuf>, delay: Duration, on_change: impl Fn(Vec<PathBuf>)) {
+    while let Ok(path) = receiver.recv() {
+        let mut paths = BTreeSet::from([path]);
+        loop {
+            match receiver.recv_timeout(delay) {
+                Ok(path) => {
+                    paths.insert(path);
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    on_change(paths.into_iter().collect());
+                    return;
+                }
+            }
+        }
+        on_change(paths.into_iter().collect());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn test_debounce() {
+        let (sender, receiver) = mpsc::channel();
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let handle = {
+            let batches = batches.clone();
+            thread::spawn(move || {
+                debounce(receiver, Duration::from_millis(100), move |paths| {
+                    batches.lock().unwrap().push(paths)
+                })
+            })
+        };
+
+        for _ in 0..3 {
+            sender.send(PathBuf::from("setting.json")).unwrap();
+        }
+        sender.send(PathBuf::from("a/rev/settings.json")).unwrap();
+        thread::sleep(Duration::from_millis(400));
+        sender.send(PathBuf::from("setting.json")).unwrap();
+        drop(sender);
+        handle.join().unwrap();
+
+        assert_eq!(
+            *batches.lock().unwrap(),
+            vec![
+                vec![
+                    PathBuf::from("a/rev/settings.json"),
+                    PathBuf::from("setting.json")
+                ],
+                vec![PathBuf::from("setting.json")],
+            ]
+        );
+    }
+}